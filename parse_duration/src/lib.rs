@@ -53,10 +53,7 @@ pub fn parse(value: &str) -> Result<Duration, Error> {
 
 #[deprecated(note = "Please use `parse` instead")]
 pub fn parse_duration(value: &str) -> Option<Duration> {
-    match parse(value) {
-        Ok(duration) => Some(duration),
-        _ => None,
-    }
+    parse(value).ok()
 }
 
 #[cfg(test)]
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use reqwest::Response;
 use serde::Deserialize;
 
@@ -11,19 +13,26 @@ struct ActuatorResponse {
 
 pub struct Checker<'a> {
     check_config: &'a CheckConfig,
+    timeout: Duration,
 }
 
 impl Checker<'_> {
-    pub fn new(check_config: &CheckConfig) -> Checker {
-        Checker { check_config }
+    pub fn new(check_config: &CheckConfig, timeout: Duration) -> Checker<'_> {
+        Checker {
+            check_config,
+            timeout,
+        }
     }
 }
 
 impl HttpBasedChecker for Checker<'_> {
-    async fn check_response(response: Response) -> CheckState {
-        if response.status().is_success() {
-            return match response.json::<ActuatorResponse>().await {
-                Ok(ar) if ar.status == "UP" => CheckState::Up,
+    async fn check_response(&self, response: Response) -> CheckState {
+        if self.check_config.is_expected_status(response.status()) {
+            let body = response.text().await.unwrap_or_default();
+            return match serde_json::from_str::<ActuatorResponse>(&body) {
+                Ok(ar) if ar.status == "UP" && self.check_config.is_expected_body(&body) => {
+                    CheckState::Up
+                }
                 _ => CheckState::Warn,
             };
         }
@@ -33,14 +42,30 @@ impl HttpBasedChecker for Checker<'_> {
     fn get_check_config(&self) -> &CheckConfig {
         self.check_config
     }
+
+    fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::checker::actuator::Checker;
     use crate::checker::{CheckState, HttpBasedChecker};
+    use crate::config::CheckConfig;
     use reqwest::Response;
     use serde_json::json;
+    use std::time::Duration;
+
+    fn check_config() -> CheckConfig {
+        toml::from_str(
+            r#"
+                name = "test"
+                url = "http://localhost/actuator/health"
+            "#,
+        )
+        .unwrap()
+    }
 
     #[tokio::test]
     async fn test_should_return_up_state() {
@@ -50,7 +75,9 @@ mod tests {
                 .body(json!({"status":"UP"}).to_string())
                 .unwrap(),
         );
-        let check_state = Checker::check_response(response).await;
+        let check_state = Checker::new(&check_config(), Duration::from_secs(1))
+            .check_response(response)
+            .await;
 
         assert_eq!(check_state, CheckState::Up)
     }
@@ -63,7 +90,9 @@ mod tests {
                 .body(json!({"status":"DOWN"}).to_string())
                 .unwrap(),
         );
-        let check_state = Checker::check_response(response).await;
+        let check_state = Checker::new(&check_config(), Duration::from_secs(1))
+            .check_response(response)
+            .await;
 
         assert_eq!(check_state, CheckState::Warn)
     }
@@ -76,7 +105,32 @@ mod tests {
                 .body(String::from("Actuator Response Not Found"))
                 .unwrap(),
         );
-        let check_state = Checker::check_response(response).await;
+        let check_state = Checker::new(&check_config(), Duration::from_secs(1))
+            .check_response(response)
+            .await;
+
+        assert_eq!(check_state, CheckState::Warn)
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_on_failed_body_assertion() {
+        let check_config: CheckConfig = toml::from_str(
+            r#"
+                name = "test"
+                url = "http://localhost/actuator/health"
+                json_path = '$.components.db.status == "UP"'
+            "#,
+        )
+        .unwrap();
+        let response = Response::from(
+            http::Response::builder()
+                .status(200)
+                .body(json!({"status":"UP","components":{"db":{"status":"DOWN"}}}).to_string())
+                .unwrap(),
+        );
+        let check_state = Checker::new(&check_config, Duration::from_secs(1))
+            .check_response(response)
+            .await;
 
         assert_eq!(check_state, CheckState::Warn)
     }
@@ -0,0 +1,165 @@
+use std::process::Stdio;
+use std::time::Duration;
+
+use nix::sys::signal::{killpg, Signal};
+use nix::unistd::Pid;
+use tokio::process::Command;
+use tokio::time::{timeout, Instant};
+
+use crate::checker::{CheckResult, CheckState};
+use crate::config::CheckConfig;
+
+pub struct Checker<'a> {
+    check_config: &'a CheckConfig,
+    timeout: Duration,
+}
+
+impl Checker<'_> {
+    pub fn new(check_config: &CheckConfig, timeout: Duration) -> Checker<'_> {
+        Checker {
+            check_config,
+            timeout,
+        }
+    }
+
+    /// Runs configured shell command and maps its exit code like Nagios plugins:
+    /// 0 is up, 1 is warn, 2 is down and any other exit code is unknown.
+    /// The first line of its output, without performance data, is used as message.
+    pub async fn check(&self) -> CheckResult {
+        let started = Instant::now();
+        let Some(command) = &self.check_config.command else {
+            return CheckResult::new(self.check_config, CheckState::Unknown, started);
+        };
+
+        // Command runs in its own process group, which will be killed if not completed within timeout
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .process_group(0)
+            .kill_on_drop(true)
+            .spawn();
+        let output = async {
+            let child = child?;
+            let mut process_group = ProcessGroup(child.id());
+            let output = child.wait_with_output().await;
+            process_group.0 = None;
+            output
+        };
+
+        let (state, message) = match timeout(self.timeout, output).await {
+            Ok(Ok(output)) => {
+                let state = match output.status.code() {
+                    Some(0) => CheckState::Up,
+                    Some(1) => CheckState::Warn,
+                    Some(2) => CheckState::Down,
+                    _ => CheckState::Unknown,
+                };
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let message = stdout
+                    .lines()
+                    .next()
+                    .and_then(|line| line.split('|').next())
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_string);
+                (state, message)
+            }
+            Ok(Err(err)) => (CheckState::Unknown, Some(err.to_string())),
+            Err(_) => (CheckState::Timeout, None),
+        };
+
+        let mut check_result = CheckResult::new(self.check_config, state, started);
+        check_result.message = message;
+        check_result
+    }
+}
+
+/// Process group of a running command, which is killed when dropped, e.g. on timeout. This also kills processes
+/// started by the command, which would keep running if only the shell was killed.
+struct ProcessGroup(Option<u32>);
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0.and_then(|pid| i32::try_from(pid).ok()) {
+            let _ = killpg(Pid::from_raw(pid), Signal::SIGKILL);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::checker::command::Checker;
+    use crate::checker::{test_check_config, CheckState};
+    use crate::config::{CheckConfig, CheckType};
+
+    fn check_config(command: &str) -> CheckConfig {
+        let mut check_config = test_check_config(CheckType::Command, "");
+        check_config.command = Some(command.to_string());
+        check_config
+    }
+
+    #[tokio::test]
+    async fn test_should_map_exit_codes() {
+        for (exit_code, state) in [
+            (0, CheckState::Up),
+            (1, CheckState::Warn),
+            (2, CheckState::Down),
+            (3, CheckState::Unknown),
+            (127, CheckState::Unknown),
+        ] {
+            let check_result = Checker::new(
+                &check_config(&format!("exit {exit_code}")),
+                Duration::from_secs(1),
+            )
+            .check()
+            .await;
+
+            assert_eq!(check_result.state, state);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_should_use_first_line_of_output_as_message() {
+        let check_config =
+            check_config(r#"printf "LOAD WARNING - load average: 4.2 | load=4.2\nmore\n"; exit 1"#);
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Warn);
+        assert_eq!(
+            check_result.message,
+            Some("LOAD WARNING - load average: 4.2".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_return_timeout_state_on_long_running_command() {
+        let check_result = Checker::new(&check_config("sleep 5"), Duration::from_millis(100))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Timeout);
+    }
+
+    #[tokio::test]
+    async fn test_should_kill_processes_started_by_command_on_timeout() {
+        let marker = std::env::temp_dir().join(format!("checkbar-{}-command", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        let command = format!("(sleep 0.3; touch {}) & wait", marker.display());
+
+        let check_result = Checker::new(&check_config(&command), Duration::from_millis(100))
+            .check()
+            .await;
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        assert_eq!(check_result.state, CheckState::Timeout);
+        assert!(!marker.exists());
+    }
+}
@@ -0,0 +1,320 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use hickory_resolver::config::{NameServerConfigGroup, ResolveHosts, ResolverConfig};
+use hickory_resolver::name_server::TokioConnectionProvider;
+use hickory_resolver::proto::op::ResponseCode;
+use hickory_resolver::proto::rr::{RData, RecordType};
+use hickory_resolver::proto::ProtoErrorKind;
+use hickory_resolver::{Name, ResolveError, Resolver};
+use reqwest::Url;
+use tokio::time::{timeout, Instant};
+
+use crate::checker::{CheckResult, CheckState};
+use crate::config::CheckConfig;
+
+pub struct Checker<'a> {
+    check_config: &'a CheckConfig,
+    timeout: Duration,
+}
+
+impl Checker<'_> {
+    pub fn new(check_config: &CheckConfig, timeout: Duration) -> Checker<'_> {
+        Checker {
+            check_config,
+            timeout,
+        }
+    }
+
+    pub async fn check(&self) -> CheckResult {
+        let started = Instant::now();
+        let query = match Query::parse(&self.check_config.url) {
+            Ok(query) => query,
+            Err(message) => {
+                let mut check_result =
+                    CheckResult::new(self.check_config, CheckState::Down, started);
+                check_result.message = Some(message);
+                return check_result;
+            }
+        };
+
+        let (state, message) = match timeout(self.timeout, self.resolve(&query)).await {
+            Ok(Ok(answers)) if answers.is_empty() => {
+                (CheckState::Down, Some("no answer".to_string()))
+            }
+            Ok(Ok(answers)) => {
+                let is_expected = query.expect.iter().all(|expected| {
+                    answers
+                        .iter()
+                        .any(|answer| normalize(answer).eq_ignore_ascii_case(expected))
+                });
+                let state = if is_expected {
+                    CheckState::Up
+                } else {
+                    CheckState::Warn
+                };
+                (state, Some(answers.join(", ")))
+            }
+            Ok(Err(message)) => (CheckState::Down, Some(message)),
+            Err(_) => (CheckState::Timeout, None),
+        };
+
+        let mut check_result = CheckResult::new(self.check_config, state, started);
+        check_result.message = message;
+        check_result
+    }
+
+    /// Sends query to its DNS server, or those of system resolver configuration, and returns answers of
+    /// requested record type. Truncated responses are retried using TCP.
+    async fn resolve(&self, query: &Query) -> Result<Vec<String>, String> {
+        let mut resolver = match query.server {
+            Some(server) => Resolver::builder_with_config(
+                ResolverConfig::from_parts(
+                    None,
+                    vec![],
+                    NameServerConfigGroup::from_ips_clear(&[server.ip()], server.port(), true),
+                ),
+                TokioConnectionProvider::default(),
+            ),
+            None => Resolver::builder_tokio().map_err(|err| err.to_string())?,
+        };
+        let options = resolver.options_mut();
+        options.timeout = self.timeout;
+        options.cache_size = 0;
+        options.use_hosts_file = ResolveHosts::Never;
+
+        let lookup = resolver
+            .build()
+            .lookup(query.name.clone(), query.record_type)
+            .await
+            .map_err(|err| failure_reason(&err))?;
+        Ok(lookup
+            .record_iter()
+            // Skip other records, e.g. CNAME records leading to requested A records
+            .filter(|record| record.record_type() == query.record_type)
+            .filter_map(|record| format_record_data(record.data()))
+            .collect())
+    }
+}
+
+/// Supported record types.
+const RECORD_TYPES: [RecordType; 6] = [
+    RecordType::A,
+    RecordType::NS,
+    RecordType::CNAME,
+    RecordType::MX,
+    RecordType::TXT,
+    RecordType::AAAA,
+];
+
+/// Returns record type of given name, if supported.
+fn record_type(name: &str) -> Option<RecordType> {
+    RECORD_TYPES
+        .into_iter()
+        .find(|record_type| record_type.to_string().eq_ignore_ascii_case(name))
+}
+
+/// Returns a problem found in DNS query of given URL, if any.
+pub fn validate_url(url: &str) -> Result<(), String> {
+    Query::parse(url).map(|_| ())
+}
+
+/// Parses DNS server address like `10.0.0.53`, `10.0.0.53:5353` or `[fd00::53]:53`.
+fn parse_server(server: &str) -> Option<SocketAddr> {
+    server.parse::<SocketAddr>().ok().or_else(|| {
+        server
+            .parse::<IpAddr>()
+            .ok()
+            .map(|ip| SocketAddr::new(ip, 53))
+    })
+}
+
+/// DNS query parsed from check URL like `dns://hostname?type=A&expect=10.0.0.5&server=10.0.0.53`.
+struct Query {
+    name: Name,
+    record_type: RecordType,
+    expect: Vec<String>,
+    server: Option<SocketAddr>,
+}
+
+impl Query {
+    fn parse(url: &str) -> Result<Self, String> {
+        let url = Url::parse(url).map_err(|err| err.to_string())?;
+        let name = url.host_str().ok_or("missing hostname")?;
+        let mut query = Query {
+            name: Name::from_ascii(name)
+                .map_err(|err| format!("invalid hostname '{name}': {err}"))?,
+            record_type: RecordType::A,
+            expect: vec![],
+            server: None,
+        };
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "type" => {
+                    query.record_type = record_type(&value)
+                        .ok_or_else(|| format!("unsupported record type '{value}'"))?
+                }
+                "expect" => query
+                    .expect
+                    .extend(value.split(',').map(|value| normalize(value.trim()))),
+                "server" => {
+                    query.server = Some(
+                        parse_server(&value).ok_or_else(|| format!("invalid server '{value}'"))?,
+                    )
+                }
+                _ => {}
+            }
+        }
+        Ok(query)
+    }
+}
+
+/// Normalizes an answer or expected value, so IP addresses and names can be compared.
+fn normalize(value: &str) -> String {
+    match value.parse::<IpAddr>() {
+        Ok(ip) => ip.to_string(),
+        Err(_) => value.trim_end_matches('.').to_string(),
+    }
+}
+
+/// Formats record data of supported record types.
+fn format_record_data(data: &RData) -> Option<String> {
+    let name = |name: &Name| name.to_utf8().trim_end_matches('.').to_string();
+    match data {
+        RData::A(ip) => Some(ip.to_string()),
+        RData::AAAA(ip) => Some(ip.to_string()),
+        RData::NS(ns) => Some(name(ns)),
+        RData::CNAME(cname) => Some(name(cname)),
+        RData::MX(mx) => Some(name(mx.exchange())),
+        RData::TXT(txt) => Some(
+            txt.txt_data()
+                .iter()
+                .map(|part| String::from_utf8_lossy(part))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Returns reason of a failed lookup, e.g. `NXDOMAIN` or `SERVFAIL` as response code of DNS server.
+fn failure_reason(err: &ResolveError) -> String {
+    match err.proto().map(|err| err.kind()) {
+        Some(ProtoErrorKind::NoRecordsFound {
+            response_code: ResponseCode::NoError,
+            ..
+        }) => "no answer".to_string(),
+        Some(ProtoErrorKind::NoRecordsFound {
+            response_code: ResponseCode::Unknown(rcode),
+            ..
+        }) => format!("RCODE {rcode}"),
+        Some(ProtoErrorKind::NoRecordsFound { response_code, .. }) => {
+            format!("{response_code:?}").to_uppercase()
+        }
+        _ => err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::net::UdpSocket;
+
+    use crate::checker::dns::{validate_url, Checker};
+    use crate::checker::{answer_datagram, test_check_config, CheckState};
+    use crate::config::CheckType;
+
+    /// Answers a single DNS query using given response code and A record, returns server address.
+    async fn serve_dns(rcode: u8, answer: [u8; 4]) -> String {
+        let (server, _) = answer_datagram(move |query| {
+            let mut response = query.to_vec();
+            response[2] = 0x81;
+            response[3] = 0x80 | rcode;
+            if rcode == 0 {
+                response[7] = 1;
+                response.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+                response.extend_from_slice(&answer);
+            }
+            response
+        })
+        .await;
+        server
+    }
+
+    #[test]
+    fn test_should_validate_hostname() {
+        assert!(validate_url("dns://app.example.com?type=MX").is_ok());
+        assert!(validate_url(&format!("dns://{}.example.com", "a".repeat(63))).is_ok());
+        assert!(validate_url(&format!("dns://{}.example.com", "a".repeat(64))).is_err());
+        assert_eq!(
+            validate_url("dns://app.example.com?type=SRV"),
+            Err("unsupported record type 'SRV'".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_return_up_state_on_expected_answer() {
+        let server = serve_dns(0, [10, 0, 0, 5]).await;
+        let check_config = test_check_config(
+            CheckType::Dns,
+            &format!("dns://app.example.com?type=A&expect=10.0.0.5&server={server}"),
+        );
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+        assert_eq!(check_result.message, Some("10.0.0.5".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_on_unexpected_answer() {
+        let server = serve_dns(0, [192, 0, 2, 1]).await;
+        let check_config = test_check_config(
+            CheckType::Dns,
+            &format!("dns://app.example.com?expect=10.0.0.5&server={server}"),
+        );
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Warn);
+        assert_eq!(check_result.message, Some("192.0.2.1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_should_return_down_state_on_nxdomain() {
+        let server = serve_dns(3, [0; 4]).await;
+        let check_config = test_check_config(
+            CheckType::Dns,
+            &format!("dns://app.example.com?server={server}"),
+        );
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Down);
+        assert_eq!(check_result.message, Some("NXDOMAIN".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_should_return_timeout_state_on_missing_response() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let check_config = test_check_config(
+            CheckType::Dns,
+            &format!(
+                "dns://app.example.com?server={}",
+                socket.local_addr().unwrap()
+            ),
+        );
+
+        let check_result = Checker::new(&check_config, Duration::from_millis(100))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Timeout);
+    }
+}
@@ -0,0 +1,479 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use http::{HeaderMap, Request, StatusCode};
+use percent_encoding::percent_decode;
+use reqwest::Url;
+use rustls::pki_types::ServerName;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Instant};
+use tokio_rustls::TlsConnector;
+
+use crate::checker::tls_cert::client_config;
+use crate::checker::{tls_failure_reason, url_host, CheckResult, CheckState};
+use crate::config::CheckConfig;
+
+const HEALTH_CHECK_PATH: &str = "/grpc.health.v1.Health/Check";
+
+pub struct Checker<'a> {
+    check_config: &'a CheckConfig,
+    timeout: Duration,
+}
+
+impl Checker<'_> {
+    pub fn new(check_config: &CheckConfig, timeout: Duration) -> Checker<'_> {
+        Checker {
+            check_config,
+            timeout,
+        }
+    }
+
+    /// Maps serving status of gRPC health checking protocol: `SERVING` is up, `NOT_SERVING` is down
+    /// and `UNKNOWN` or `SERVICE_UNKNOWN` is warn. Failed calls, e.g. `NOT_FOUND` for unknown services,
+    /// and servers answering without status also result in warn, except `UNAVAILABLE`, which is down.
+    pub async fn check(&self) -> CheckResult {
+        let started = Instant::now();
+        let (state, message) = match timeout(self.timeout, self.health_check()).await {
+            Ok(Ok(Reply::Status(1))) => (CheckState::Up, None),
+            Ok(Ok(Reply::Status(2))) => (CheckState::Down, Some("NOT_SERVING".to_string())),
+            Ok(Ok(Reply::Status(3))) => (CheckState::Warn, Some("SERVICE_UNKNOWN".to_string())),
+            Ok(Ok(Reply::Status(_))) => (CheckState::Warn, Some("UNKNOWN".to_string())),
+            Ok(Ok(Reply::Error(code, message))) => {
+                let name = STATUS_CODES
+                    .get(code as usize)
+                    .copied()
+                    .unwrap_or("UNKNOWN");
+                let message = match message.is_empty() {
+                    true => name.to_string(),
+                    false => format!("{name}: {message}"),
+                };
+                let state = match code {
+                    STATUS_UNAVAILABLE => CheckState::Down,
+                    _ => CheckState::Warn,
+                };
+                (state, Some(message))
+            }
+            Ok(Ok(Reply::Empty)) => (
+                CheckState::Warn,
+                Some("no health status received".to_string()),
+            ),
+            Ok(Err(message)) => (CheckState::Down, Some(message)),
+            Err(_) => (CheckState::Timeout, None),
+        };
+
+        let mut check_result = CheckResult::new(self.check_config, state, started);
+        check_result.message = message;
+        check_result
+    }
+
+    /// Calls `grpc.health.v1.Health/Check` for the service given as URL path, if any,
+    /// and returns the reply.
+    async fn health_check(&self) -> Result<Reply, String> {
+        let url = Url::parse(&self.check_config.url).map_err(|err| err.to_string())?;
+        let (Some(host), Some(port)) = (url_host(&url), url.port()) else {
+            return Err("URL must contain host and port".to_string());
+        };
+        // Other than the host to connect to, the authority keeps brackets of IPv6 addresses
+        let authority = format!("{}:{port}", url.host_str().unwrap_or(host));
+        let service = url.path().trim_start_matches('/');
+
+        let tcp_stream = TcpStream::connect((host, port))
+            .await
+            .map_err(|err| format!("connection failed: {err}"))?;
+        if url.scheme() != "grpcs" {
+            return call(tcp_stream, "http", &authority, service).await;
+        }
+
+        let mut client_config = client_config(self.check_config).await?;
+        client_config.alpn_protocols = vec![b"h2".to_vec()];
+        let server_name = ServerName::try_from(host.to_string()).map_err(|err| err.to_string())?;
+        let tls_stream = TlsConnector::from(Arc::new(client_config))
+            .connect(server_name, tcp_stream)
+            .await
+            .map_err(|err| {
+                tls_failure_reason(&err).unwrap_or_else(|| format!("connection failed: {err}"))
+            })?;
+        call(tls_stream, "https", &authority, service).await
+    }
+}
+
+/// Reply to a health check call.
+#[derive(Debug, PartialEq)]
+enum Reply {
+    /// Serving status of the health check response.
+    Status(u64),
+    /// gRPC status code and message of a failed call, e.g. `UNIMPLEMENTED` if the server has no health checks.
+    Error(u32, String),
+    /// Neither serving status nor error, e.g. an empty response.
+    Empty,
+}
+
+/// Names of gRPC status codes.
+const STATUS_CODES: [&str; 17] = [
+    "OK",
+    "CANCELLED",
+    "UNKNOWN",
+    "INVALID_ARGUMENT",
+    "DEADLINE_EXCEEDED",
+    "NOT_FOUND",
+    "ALREADY_EXISTS",
+    "PERMISSION_DENIED",
+    "RESOURCE_EXHAUSTED",
+    "FAILED_PRECONDITION",
+    "ABORTED",
+    "OUT_OF_RANGE",
+    "UNIMPLEMENTED",
+    "INTERNAL",
+    "UNAVAILABLE",
+    "DATA_LOSS",
+    "UNAUTHENTICATED",
+];
+const STATUS_UNAVAILABLE: u32 = 14;
+
+/// Sends health check request over HTTP/2 and returns the status of the response message, or the gRPC status
+/// of response headers or trailers if the call failed.
+async fn call<S>(stream: S, scheme: &str, authority: &str, service: &str) -> Result<Reply, String>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let mut request = Vec::new();
+    if !service.is_empty() {
+        request.push(0x0a);
+        request.extend(varint(service.len() as u64));
+        request.extend(service.as_bytes());
+    }
+    let mut message = vec![0];
+    message.extend((request.len() as u32).to_be_bytes());
+    message.extend(request);
+
+    let (client, connection) = h2::client::handshake(stream)
+        .await
+        .map_err(|err| err.to_string())?;
+    // Connection is closed as soon as client and response are dropped
+    tokio::spawn(connection);
+    let mut client = client.ready().await.map_err(|err| err.to_string())?;
+    let request = Request::post(format!("{scheme}://{authority}{HEALTH_CHECK_PATH}"))
+        .header("content-type", "application/grpc")
+        .header("te", "trailers")
+        .body(())
+        .map_err(|err| err.to_string())?;
+    let (response, mut send_stream) = client
+        .send_request(request, false)
+        .map_err(|err| err.to_string())?;
+    send_stream
+        .send_data(Bytes::from(message), true)
+        .map_err(|err| err.to_string())?;
+
+    let response = response.await.map_err(|err| err.to_string())?;
+    if response.status() != StatusCode::OK {
+        return Err(format!("HTTP status {}", response.status()));
+    }
+    // Trailers-only responses contain gRPC status within headers
+    if let Some(reply) = grpc_status(response.headers()) {
+        return Ok(reply);
+    }
+
+    let mut body = response.into_body();
+    let mut data = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|err| err.to_string())?;
+        let _ = body.flow_control().release_capacity(chunk.len());
+        data.extend(chunk);
+        if let Some(message) = grpc_message(&data) {
+            return Ok(Reply::Status(serving_status(message)));
+        }
+    }
+    let trailers = body.trailers().await.map_err(|err| err.to_string())?;
+    Ok(trailers
+        .and_then(|trailers| grpc_status(&trailers))
+        .unwrap_or(Reply::Empty))
+}
+
+/// Returns error of a failed call given by `grpc-status` and `grpc-message`, if any.
+fn grpc_status(headers: &HeaderMap) -> Option<Reply> {
+    let code = headers
+        .get("grpc-status")?
+        .to_str()
+        .ok()?
+        .parse::<u32>()
+        .ok()?;
+    if code == 0 {
+        return None;
+    }
+    let message = headers
+        .get("grpc-message")
+        .map(|message| {
+            percent_decode(message.as_bytes())
+                .decode_utf8_lossy()
+                .to_string()
+        })
+        .unwrap_or_default();
+    Some(Reply::Error(code, message))
+}
+
+fn varint(mut value: u64) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    while value >= 0x80 {
+        bytes.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+    bytes
+}
+
+/// Reads protobuf varint at start of given data and returns its value and remaining data.
+fn read_varint(data: &[u8]) -> Option<(u64, &[u8])> {
+    let mut value = 0;
+    for (i, byte) in data.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, &data[i + 1..]));
+        }
+    }
+    None
+}
+
+/// Returns the first length-prefixed gRPC message, if completely received.
+fn grpc_message(data: &[u8]) -> Option<&[u8]> {
+    let len = u32::from_be_bytes(data.get(1..5)?.try_into().ok()?) as usize;
+    data.get(5..5 + len)
+}
+
+/// Returns field `status` of `HealthCheckResponse`, which defaults to `UNKNOWN`.
+fn serving_status(mut message: &[u8]) -> u64 {
+    while let Some((key, rest)) = read_varint(message) {
+        let rest = match key & 0x7 {
+            0 => match read_varint(rest) {
+                Some((value, _)) if key >> 3 == 1 => return value,
+                Some((_, rest)) => Some(rest),
+                None => None,
+            },
+            1 => rest.get(8..),
+            2 => read_varint(rest).and_then(|(len, rest)| rest.get(len as usize..)),
+            5 => rest.get(4..),
+            _ => None,
+        };
+        match rest {
+            Some(rest) => message = rest,
+            None => break,
+        }
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+    use std::time::Duration;
+
+    use bytes::Bytes;
+    use http::{HeaderMap, Response};
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    use crate::checker::grpc::{serving_status, Checker};
+    use crate::checker::{accept_once, test_check_config, tls_acceptor, CheckState};
+    use crate::config::CheckType;
+
+    /// Answers a single gRPC call using given serving status and `grpc-status` in trailers. Without serving
+    /// status, a trailers-only response is sent. Returns the request message.
+    async fn answer<S>(
+        stream: S,
+        status: Option<u8>,
+        trailers: &'static [(&'static str, &'static str)],
+    ) -> Vec<u8>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let mut connection = h2::server::handshake(stream).await.unwrap();
+        let (request, mut respond) = connection.accept().await.unwrap().unwrap();
+        tokio::spawn(async move { poll_fn(|cx| connection.poll_closed(cx)).await });
+
+        let mut body = request.into_body();
+        let mut request = Vec::new();
+        while let Some(chunk) = body.data().await {
+            request.extend(chunk.unwrap());
+        }
+
+        let mut response = Response::builder().header("content-type", "application/grpc");
+        let mut trailer_map = HeaderMap::new();
+        for (name, value) in trailers {
+            trailer_map.insert(*name, value.parse().unwrap());
+        }
+        match status {
+            Some(status) => {
+                let mut send_stream = respond
+                    .send_response(response.body(()).unwrap(), false)
+                    .unwrap();
+                send_stream
+                    .send_data(Bytes::from(vec![0, 0, 0, 0, 2, 0x08, status]), false)
+                    .unwrap();
+                send_stream.send_trailers(trailer_map).unwrap();
+            }
+            None => {
+                response.headers_mut().unwrap().extend(trailer_map);
+                respond
+                    .send_response(response.body(()).unwrap(), true)
+                    .unwrap();
+            }
+        }
+        request
+    }
+
+    /// Accepts a single HTTP/2 connection and answers the call as described for `answer`.
+    /// Returns address and handle to receive the request message.
+    async fn serve_grpc(
+        status: Option<u8>,
+        trailers: &'static [(&'static str, &'static str)],
+    ) -> (String, JoinHandle<Vec<u8>>) {
+        accept_once(move |stream| async move { answer(stream, status, trailers).await }).await
+    }
+
+    #[test]
+    fn test_should_read_serving_status() {
+        assert_eq!(serving_status(&[0x08, 0x01]), 1);
+        assert_eq!(serving_status(&[0x12, 0x02, 0x61, 0x62, 0x08, 0x02]), 2);
+        assert_eq!(serving_status(&[]), 0);
+    }
+
+    #[tokio::test]
+    async fn test_should_return_up_state_if_serving() {
+        let (address, request) = serve_grpc(Some(1), &[("grpc-status", "0")]).await;
+
+        let check_result = Checker::new(
+            &test_check_config(CheckType::Grpc, &format!("grpc://{address}/app.Orders")),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+        assert_eq!(
+            request.await.unwrap(),
+            b"\x00\x00\x00\x00\x0c\x0a\x0aapp.Orders".to_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_map_serving_status() {
+        for (status, state) in [
+            (0, CheckState::Warn),
+            (2, CheckState::Down),
+            (3, CheckState::Warn),
+        ] {
+            let (address, _) = serve_grpc(Some(status), &[("grpc-status", "0")]).await;
+
+            let check_result = Checker::new(
+                &test_check_config(CheckType::Grpc, &format!("grpc://{address}")),
+                Duration::from_secs(1),
+            )
+            .check()
+            .await;
+
+            assert_eq!(check_result.state, state);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_without_status() {
+        let (address, _) = serve_grpc(None, &[("grpc-status", "0")]).await;
+
+        let check_result = Checker::new(
+            &test_check_config(CheckType::Grpc, &format!("grpc://{address}")),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Warn);
+        assert_eq!(
+            check_result.message,
+            Some("no health status received".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_decode_grpc_status_of_failed_call() {
+        for (trailers, state, message) in [
+            (
+                &[("grpc-status", "5"), ("grpc-message", "unknown%20service")][..],
+                CheckState::Warn,
+                "NOT_FOUND: unknown service",
+            ),
+            (
+                &[("grpc-status", "12")][..],
+                CheckState::Warn,
+                "UNIMPLEMENTED",
+            ),
+            (
+                &[("grpc-status", "14")][..],
+                CheckState::Down,
+                "UNAVAILABLE",
+            ),
+        ] {
+            let (address, _) = serve_grpc(None, trailers).await;
+
+            let check_result = Checker::new(
+                &test_check_config(CheckType::Grpc, &format!("grpc://{address}/app.Orders")),
+                Duration::from_secs(1),
+            )
+            .check()
+            .await;
+
+            assert_eq!(check_result.state, state);
+            assert_eq!(check_result.message, Some(message.to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_should_use_client_certificate_for_grpcs() {
+        let acceptor = tls_acceptor("server", true, vec![b"h2".to_vec()]);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                if let Ok(stream) = acceptor.accept(stream).await {
+                    answer(stream, Some(1), &[("grpc-status", "0")]).await;
+                }
+            }
+        });
+        let url = format!("grpcs://localhost:{}", address.port());
+        let mut check_config = test_check_config(CheckType::Grpc, &url);
+        check_config.ca_file = Some("tests/tls/ca.pem".to_string());
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+        assert_eq!(check_result.state, CheckState::Down);
+
+        check_config.client_cert = Some("tests/tls/client.pem".to_string());
+        check_config.client_key = Some("tests/tls/client.key".to_string());
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+        assert_eq!(check_result.state, CheckState::Up);
+    }
+
+    #[tokio::test]
+    async fn test_should_return_down_state_if_not_http2() {
+        let (address, _) = accept_once(|mut stream| async move {
+            let _ = stream
+                .write_all(b"HTTP/1.1 400 Bad Request\r\nConnection: close\r\n\r\n")
+                .await;
+            let _ = stream.read(&mut [0; 1024]).await;
+        })
+        .await;
+
+        let check_result = Checker::new(
+            &test_check_config(CheckType::Grpc, &format!("grpc://{address}")),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Down);
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use reqwest::Response;
 
 use crate::checker::{CheckState, HttpBasedChecker};
@@ -5,32 +7,60 @@ use crate::config::CheckConfig;
 
 pub struct Checker<'a> {
     check_config: &'a CheckConfig,
+    timeout: Duration,
 }
 
 impl Checker<'_> {
-    pub fn new(check_config: &CheckConfig) -> Checker {
-        Checker { check_config }
+    pub fn new(check_config: &CheckConfig, timeout: Duration) -> Checker<'_> {
+        Checker {
+            check_config,
+            timeout,
+        }
     }
 }
 
 impl HttpBasedChecker for Checker<'_> {
-    async fn check_response(response: Response) -> CheckState {
-        if response.status().is_success() {
+    async fn check_response(&self, response: Response) -> CheckState {
+        if !self.check_config.is_expected_status(response.status()) {
+            return CheckState::Warn;
+        }
+        if !self.check_config.has_body_assertions() {
             return CheckState::Up;
         }
-        CheckState::Warn
+        match response.text().await {
+            Ok(body) if self.check_config.is_expected_body(&body) => CheckState::Up,
+            _ => CheckState::Warn,
+        }
     }
 
     fn get_check_config(&self) -> &CheckConfig {
         self.check_config
     }
+
+    fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::checker::http::Checker;
-    use crate::checker::{CheckState, HttpBasedChecker};
+    use crate::checker::{serve_once, serve_tls, CheckState, HttpBasedChecker};
+    use crate::config::CheckConfig;
     use reqwest::Response;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn check_config() -> CheckConfig {
+        toml::from_str(
+            r#"
+                name = "test"
+                url = "http://localhost"
+            "#,
+        )
+        .unwrap()
+    }
 
     #[tokio::test]
     async fn test_should_return_up_state() {
@@ -40,7 +70,9 @@ mod tests {
                 .body("Any response")
                 .unwrap(),
         );
-        let check_state = Checker::check_response(response).await;
+        let check_state = Checker::new(&check_config(), Duration::from_secs(1))
+            .check_response(response)
+            .await;
 
         assert_eq!(check_state, CheckState::Up)
     }
@@ -53,8 +85,317 @@ mod tests {
                 .body(String::from("Http Response Not Found"))
                 .unwrap(),
         );
-        let check_state = Checker::check_response(response).await;
+        let check_state = Checker::new(&check_config(), Duration::from_secs(1))
+            .check_response(response)
+            .await;
+
+        assert_eq!(check_state, CheckState::Warn)
+    }
+
+    #[tokio::test]
+    async fn test_should_return_timeout_state_on_missing_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let check_config: CheckConfig = toml::from_str(&format!(
+            "name = \"test\"\nurl = \"http://{}\"",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+
+        let check_result = Checker::new(&check_config, Duration::from_millis(100))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Timeout)
+    }
+
+    #[tokio::test]
+    async fn test_should_send_headers_and_basic_auth() {
+        let (url, request) = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await;
+        let check_config: CheckConfig = toml::from_str(&format!(
+            r#"
+                name = "test"
+                url = "{url}"
+                headers = {{ "X-Api-Key" = "secret" }}
+                basic_auth = {{ username = "user", password = "pass" }}
+            "#
+        ))
+        .unwrap();
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+        let request = request.await.unwrap().to_lowercase();
+
+        assert_eq!(check_result.state, CheckState::Up);
+        assert!(request.contains("x-api-key: secret\r\n"));
+        assert!(request.contains("authorization: basic dxnlcjpwyxnz\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_should_send_bearer_token() {
+        let (url, request) = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await;
+        let check_config: CheckConfig = toml::from_str(&format!(
+            r#"
+                name = "test"
+                url = "{url}"
+                bearer_token = "token"
+            "#
+        ))
+        .unwrap();
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+        let request = request.await.unwrap();
+
+        assert_eq!(check_result.state, CheckState::Up);
+        assert!(request.contains("authorization: Bearer token\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_should_return_up_state_on_expected_status() {
+        let check_config: CheckConfig = toml::from_str(
+            r#"
+                name = "test"
+                url = "http://localhost"
+                expected_status = "200-299,401"
+            "#,
+        )
+        .unwrap();
+        let response = Response::from(http::Response::builder().status(401).body("").unwrap());
+        let check_state = Checker::new(&check_config, Duration::from_secs(1))
+            .check_response(response)
+            .await;
+
+        assert_eq!(check_state, CheckState::Up)
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_on_unexpected_status() {
+        let check_config: CheckConfig = toml::from_str(
+            r#"
+                name = "test"
+                url = "http://localhost"
+                expected_status = 301
+            "#,
+        )
+        .unwrap();
+        let response = Response::from(http::Response::builder().status(200).body("").unwrap());
+        let check_state = Checker::new(&check_config, Duration::from_secs(1))
+            .check_response(response)
+            .await;
+
+        assert_eq!(check_state, CheckState::Warn)
+    }
+
+    #[tokio::test]
+    async fn test_should_not_follow_redirect_on_expected_status() {
+        let (url, _) = serve_once(
+            "HTTP/1.1 301 Moved Permanently\r\nlocation: http://127.0.0.1:1/\r\ncontent-length: 0\r\n\r\n",
+        )
+        .await;
+        let check_config: CheckConfig = toml::from_str(&format!(
+            r#"
+                name = "test"
+                url = "{url}/old"
+                expected_status = [301, 308]
+            "#
+        ))
+        .unwrap();
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+    }
+
+    #[tokio::test]
+    async fn test_should_send_method_and_body() {
+        let (url, request) = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await;
+        let check_config: CheckConfig = toml::from_str(&format!(
+            r#"
+                name = "test"
+                url = "{url}/health"
+                method = "POST"
+                body = "ping"
+            "#
+        ))
+        .unwrap();
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+        let request = request.await.unwrap();
+
+        assert_eq!(check_result.state, CheckState::Up);
+        assert!(request.starts_with("POST /health HTTP/1.1\r\n"));
+        assert!(request.ends_with("\r\n\r\nping"));
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_on_unexpected_body() {
+        let check_config: CheckConfig = toml::from_str(
+            r#"
+                name = "test"
+                url = "http://localhost"
+                json_path = '$.status == "ok"'
+            "#,
+        )
+        .unwrap();
+        let response = Response::from(
+            http::Response::builder()
+                .status(200)
+                .body(r#"{"status":"failed"}"#)
+                .unwrap(),
+        );
+        let check_state = Checker::new(&check_config, Duration::from_secs(1))
+            .check_response(response)
+            .await;
 
         assert_eq!(check_state, CheckState::Warn)
     }
+
+    #[tokio::test]
+    async fn test_should_return_up_state_on_expected_body() {
+        let check_config: CheckConfig = toml::from_str(
+            r#"
+                name = "test"
+                url = "http://localhost"
+                body_contains = "status"
+                json_path = '$.status == "ok"'
+            "#,
+        )
+        .unwrap();
+        let response = Response::from(
+            http::Response::builder()
+                .status(200)
+                .body(r#"{"status":"ok"}"#)
+                .unwrap(),
+        );
+        let check_state = Checker::new(&check_config, Duration::from_secs(1))
+            .check_response(response)
+            .await;
+
+        assert_eq!(check_state, CheckState::Up)
+    }
+
+    #[tokio::test]
+    async fn test_should_reuse_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let check_config: CheckConfig = toml::from_str(&format!(
+            "name = \"test\"\nurl = \"http://{}\"",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        // Accept a single connection only and answer all requests using it
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = [0; 1024];
+            while let Ok(len) = stream.read(&mut buffer).await {
+                if len == 0 {
+                    break;
+                }
+                let response = "HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n";
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        for _ in 0..3 {
+            let check_result = Checker::new(&check_config, Duration::from_secs(1))
+                .check()
+                .await;
+            assert_eq!(check_result.state, CheckState::Up);
+        }
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_should_return_down_state_with_certificate_error() {
+        let url = serve_tls("server", false).await;
+        let check_config: CheckConfig =
+            toml::from_str(&format!("name = \"test\"\nurl = \"{url}\"")).unwrap();
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Down);
+        assert!(check_result
+            .message
+            .unwrap()
+            .starts_with("certificate error: "));
+    }
+
+    #[tokio::test]
+    async fn test_should_return_up_state_using_ca_file() {
+        let url = serve_tls("server", false).await;
+        let check_config: CheckConfig = toml::from_str(&format!(
+            "name = \"test\"\nurl = \"{url}\"\nca_file = \"tests/tls/ca.pem\""
+        ))
+        .unwrap();
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+        assert_eq!(check_result.message, None);
+    }
+
+    #[tokio::test]
+    async fn test_should_return_up_state_using_insecure_tls() {
+        let url = serve_tls("server", false).await;
+        let check_config: CheckConfig = toml::from_str(&format!(
+            "name = \"test\"\nurl = \"{url}\"\ntls_insecure = true"
+        ))
+        .unwrap();
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+    }
+
+    #[tokio::test]
+    async fn test_should_use_client_certificate() {
+        let url = serve_tls("server", true).await;
+        let check_config: CheckConfig = toml::from_str(&format!(
+            r#"
+                name = "test"
+                url = "{url}"
+                ca_file = "tests/tls/ca.pem"
+                client_cert = "tests/tls/client.pem"
+                client_key = "tests/tls/client.key"
+            "#
+        ))
+        .unwrap();
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+    }
+
+    #[tokio::test]
+    async fn test_should_return_down_state_on_missing_client_certificate() {
+        let url = serve_tls("server", true).await;
+        let check_config: CheckConfig = toml::from_str(&format!(
+            "name = \"test\"\nurl = \"{url}\"\nca_file = \"tests/tls/ca.pem\""
+        ))
+        .unwrap();
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Down);
+        assert!(check_result
+            .message
+            .unwrap()
+            .starts_with("certificate error: "));
+    }
 }
@@ -1,129 +1,755 @@
-use std::fmt::{Display, Formatter, Result};
-
-use console::{style, Term};
-use reqwest::Response;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use console::style;
+use reqwest::redirect::Policy;
+use reqwest::tls::{Certificate, Identity};
+use reqwest::{Client, Method, Response, Url};
+use rustls::AlertDescription;
 use serde_json::json;
+use tokio::fs;
+use tokio::time::{timeout, Instant};
 
 pub use crate::checker::actuator::Checker as ActuatorChecker;
+pub use crate::checker::command::Checker as CommandChecker;
+pub use crate::checker::dns::{validate_url as validate_dns_url, Checker as DnsChecker};
+pub use crate::checker::grpc::Checker as GrpcChecker;
 pub use crate::checker::http::Checker as HttpChecker;
+pub use crate::checker::mysql::Checker as MysqlChecker;
+pub use crate::checker::postgres::Checker as PostgresChecker;
+pub use crate::checker::redis::Checker as RedisChecker;
 pub use crate::checker::tcp::Checker as TcpChecker;
-use crate::config::{CheckConfig, CheckType, Config};
+pub use crate::checker::tls_cert::Checker as TlsCertChecker;
+pub use crate::checker::udp::Checker as UdpChecker;
+use crate::config::{CheckConfig, CheckType, ColorConfig};
+use crate::output::{Output, OutputOptions};
 
 mod actuator;
+mod command;
+mod dns;
+mod grpc;
 mod http;
+mod mysql;
+mod postgres;
+mod redis;
 mod tcp;
+mod tls_cert;
+mod udp;
 
-pub async fn check_host(check_config: &CheckConfig) -> CheckResult {
+pub async fn check_host(check_config: &CheckConfig, timeout: Duration) -> CheckResult {
     match check_config.check_type {
-        Some(CheckType::Actuator) => ActuatorChecker::new(check_config).check().await,
-        Some(CheckType::Tcp) => TcpChecker::new(check_config).check().await,
-        _ => HttpChecker::new(check_config).check().await,
+        Some(CheckType::Actuator) => ActuatorChecker::new(check_config, timeout).check().await,
+        Some(CheckType::Command) => CommandChecker::new(check_config, timeout).check().await,
+        Some(CheckType::Dns) => DnsChecker::new(check_config, timeout).check().await,
+        Some(CheckType::Tcp) => TcpChecker::new(check_config, timeout).check().await,
+        Some(CheckType::TlsCert) => TlsCertChecker::new(check_config, timeout).check().await,
+        Some(CheckType::Udp) => UdpChecker::new(check_config, timeout).check().await,
+        Some(CheckType::Redis) => RedisChecker::new(check_config, timeout).check().await,
+        Some(CheckType::Postgres) => PostgresChecker::new(check_config, timeout).check().await,
+        Some(CheckType::Mysql) => MysqlChecker::new(check_config, timeout).check().await,
+        Some(CheckType::Grpc) => GrpcChecker::new(check_config, timeout).check().await,
+        _ => HttpChecker::new(check_config, timeout).check().await,
     }
 }
 
-trait ToNonTerminalString: ToString {
+trait ToNonTerminalString {
+    fn to_string(&self, color_config: Option<&ColorConfig>) -> String;
+}
+trait ToNonColoredTerminalString {
     fn to_string(&self) -> String;
 }
-trait ToNonColoredTerminalString: ToString {
+
+trait ToColoredTerminalString {
     fn to_string(&self) -> String;
 }
 
-trait ToColoredTerminalString: ToString {
+trait ToJsonString {
     fn to_string(&self) -> String;
 }
 
 pub struct CheckResult {
     pub name: String,
     pub state: CheckState,
+    pub latency: Option<Duration>,
+    pub message: Option<String>,
+}
+
+impl CheckResult {
+    /// Creates result of a check started at given time. Latency is only recorded for responding checks
+    /// in state `Up` or `Warn`, which are degraded if latency exceeds configured thresholds.
+    pub fn new(check_config: &CheckConfig, state: CheckState, started: Instant) -> Self {
+        let latency = match state {
+            CheckState::Up | CheckState::Warn => Some(started.elapsed()),
+            _ => None,
+        };
+        let state = match latency {
+            Some(latency)
+                if check_config
+                    .down_latency
+                    .is_some_and(|down| latency >= down) =>
+            {
+                CheckState::Down
+            }
+            Some(latency)
+                if check_config
+                    .warn_latency
+                    .is_some_and(|warn| latency >= warn) =>
+            {
+                CheckState::Warn
+            }
+            _ => state,
+        };
+
+        CheckResult {
+            name: check_config.name.to_string(),
+            state,
+            latency,
+            message: None,
+        }
+    }
+
+    /// Returns text shown in bar, which includes the kind of failure if a check is down because of TLS.
+    fn text(&self) -> String {
+        let failure = match (&self.state, &self.message) {
+            (CheckState::Down, Some(message)) => [CERTIFICATE_ERROR, TLS_ERROR]
+                .into_iter()
+                .find(|failure| message.starts_with(&format!("{failure}:"))),
+            _ => None,
+        };
+        match failure {
+            Some(failure) => format!("{} ({failure})", self.name),
+            None => self.name.to_string(),
+        }
+    }
+
+    /// Formats check result for given output. Colors are only used if enabled in output options.
+    pub fn format(&self, output_options: &OutputOptions, color_config: &ColorConfig) -> String {
+        match output_options.output {
+            Output::Terminal if output_options.colors => ToColoredTerminalString::to_string(self),
+            Output::Terminal => ToNonColoredTerminalString::to_string(self),
+            Output::I3bar if output_options.colors => {
+                ToNonTerminalString::to_string(self, Some(color_config))
+            }
+            Output::I3bar => ToNonTerminalString::to_string(self, None),
+            Output::Json => ToJsonString::to_string(self),
+        }
+    }
 }
 
 impl ToNonTerminalString for CheckResult {
     #[inline]
-    fn to_string(&self) -> String {
-        let color_config = Config::read().colors;
-        let color = match &self.state {
-            CheckState::Up => color_config.up,
-            CheckState::Warn => color_config.warn,
-            CheckState::Down => color_config.down,
-        };
+    fn to_string(&self, color_config: Option<&ColorConfig>) -> String {
+        let text = self.text();
+        let mut value = json!({
+            "full_text": text,
+            "name": self.name,
+            "separator_block_width": 16
+        });
+        if text != self.name {
+            value["short_text"] = json!(self.name);
+        }
+        if let Some(color_config) = color_config {
+            value["color"] = json!(match &self.state {
+                CheckState::Up => &color_config.up,
+                CheckState::Warn => &color_config.warn,
+                CheckState::Down => &color_config.down,
+                CheckState::Timeout => &color_config.timeout,
+                CheckState::Unknown => &color_config.unknown,
+            });
+        }
 
-        format!(
-            "{}",
-            json!({
-                "full_text": self.name,
-                "name": self.name,
-                "separator_block_width": 16,
-                "color": color
-            })
-        )
+        format!("{value}")
     }
 }
 
 impl ToNonColoredTerminalString for CheckResult {
     #[inline]
     fn to_string(&self) -> String {
-        self.name.to_string()
+        self.text()
+    }
+}
+
+impl ToJsonString for CheckResult {
+    #[inline]
+    fn to_string(&self) -> String {
+        let mut value = json!({
+            "name": self.name,
+            "state": format!("{:?}", self.state)
+        });
+        if let Some(latency) = self.latency {
+            value["latency_ms"] = json!(latency.as_millis());
+        }
+        if let Some(message) = &self.message {
+            value["message"] = json!(message);
+        }
+
+        format!("{value}")
     }
 }
 
 impl ToColoredTerminalString for CheckResult {
     #[inline]
     fn to_string(&self) -> String {
+        let text = self.text();
         format!(
             "{}",
             match &self.state {
-                CheckState::Up => style(&self.name).green().force_styling(true),
-                CheckState::Warn => style(&self.name).yellow().force_styling(true),
-                CheckState::Down => style(&self.name).red().force_styling(true),
+                CheckState::Up => style(text).green().force_styling(true),
+                CheckState::Warn => style(text).yellow().force_styling(true),
+                CheckState::Down => style(text).red().force_styling(true),
+                CheckState::Timeout => style(text).magenta().force_styling(true),
+                CheckState::Unknown => style(text).cyan().force_styling(true),
             }
         )
     }
 }
 
-impl Display for CheckResult {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        let term = Term::stdout();
-        if term.is_term() && term.features().colors_supported() {
-            return write!(f, "{}", ToColoredTerminalString::to_string(self));
-        } else if term.is_term() && !term.features().colors_supported() {
-            return write!(f, "{}", ToNonColoredTerminalString::to_string(self));
-        }
-        write!(f, "{}", ToNonTerminalString::to_string(self))
-    }
-}
-
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum CheckState {
     Up,
     Warn,
     Down,
+    Timeout,
+    Unknown,
+}
+
+impl CheckState {
+    /// Returns Nagios-style exit code: 0 for up, 1 for warn, 2 for down or timed out and 3 for unknown checks.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CheckState::Up => 0,
+            CheckState::Warn => 1,
+            CheckState::Down | CheckState::Timeout => 2,
+            CheckState::Unknown => 3,
+        }
+    }
+
+    /// Returns severity to find the worst of several states: down or timed out checks are worse than unknown ones,
+    /// which are worse than warnings.
+    pub fn severity(&self) -> u8 {
+        match self {
+            CheckState::Up => 0,
+            CheckState::Warn => 1,
+            CheckState::Unknown => 2,
+            CheckState::Down | CheckState::Timeout => 3,
+        }
+    }
+}
+
+/// Version of a file used by HTTP clients, which changes if the file is replaced, e.g. by a renewed
+/// certificate.
+#[derive(Hash, PartialEq, Eq)]
+struct FileVersion {
+    path: String,
+    modified: Option<SystemTime>,
+}
+
+impl FileVersion {
+    async fn read(&self) -> Result<Vec<u8>, String> {
+        fs::read(&self.path)
+            .await
+            .map_err(|err| format!("cannot read '{}': {err}", self.path))
+    }
+
+    async fn of(path: &str) -> Self {
+        FileVersion {
+            path: path.to_string(),
+            modified: fs::metadata(path)
+                .await
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+        }
+    }
+}
+
+/// HTTP client options of a check. Files are identified by path and modification time, so clients
+/// are rebuilt only if files have been changed.
+#[derive(Default, Hash, PartialEq, Eq)]
+struct ClientOptions {
+    ca_file: Option<FileVersion>,
+    client_cert: Option<FileVersion>,
+    client_key: Option<FileVersion>,
+    insecure: bool,
+    follow_redirects: bool,
+}
+
+impl ClientOptions {
+    async fn of(check_config: &CheckConfig) -> Self {
+        let (client_cert, client_key) = match (&check_config.client_cert, &check_config.client_key)
+        {
+            (Some(cert), Some(key)) => (
+                Some(FileVersion::of(cert).await),
+                Some(FileVersion::of(key).await),
+            ),
+            _ => (None, None),
+        };
+
+        ClientOptions {
+            ca_file: match &check_config.ca_file {
+                Some(ca_file) => Some(FileVersion::of(ca_file).await),
+                None => None,
+            },
+            client_cert,
+            client_key,
+            insecure: check_config.tls_insecure,
+            // Expected status codes may include redirects, so they must not be followed
+            follow_redirects: check_config.expected_status.is_none(),
+        }
+    }
+
+    /// Returns whether these options only differ from given options by modification time of files.
+    fn is_outdated_by(&self, other: &Self) -> bool {
+        let paths = |options: &Self| {
+            [&options.ca_file, &options.client_cert, &options.client_key]
+                .map(|file| file.as_ref().map(|file| file.path.clone()))
+        };
+        self != other
+            && paths(self) == paths(other)
+            && self.insecure == other.insecure
+            && self.follow_redirects == other.follow_redirects
+    }
+}
+
+static HTTP_CLIENTS: OnceLock<Mutex<HashMap<ClientOptions, Client>>> = OnceLock::new();
+
+/// Returns HTTP client for client options of given check. Clients are shared by all checks using the same
+/// options, so connections can be reused across check runs. Clients using outdated files are dropped.
+async fn http_client(check_config: &CheckConfig) -> Result<Client, String> {
+    let client_options = ClientOptions::of(check_config).await;
+    let clients = HTTP_CLIENTS.get_or_init(Default::default);
+    if let Some(client) = clients.lock().unwrap().get(&client_options) {
+        return Ok(client.clone());
+    }
+
+    let mut client_builder = Client::builder().danger_accept_invalid_certs(client_options.insecure);
+    if !client_options.follow_redirects {
+        client_builder = client_builder.redirect(Policy::none());
+    }
+    if let Some(ca_file) = &client_options.ca_file {
+        let certificates = Certificate::from_pem_bundle(&ca_file.read().await?)
+            .map_err(|err| format!("invalid CA file: {err}"))?;
+        if certificates.is_empty() {
+            return Err("invalid CA file: no certificates found".to_string());
+        }
+        for certificate in certificates {
+            client_builder = client_builder.add_root_certificate(certificate);
+        }
+    }
+    if let (Some(cert), Some(key)) = (&client_options.client_cert, &client_options.client_key) {
+        let identity = [cert.read().await?, key.read().await?].join(&b'\n');
+        client_builder = client_builder.identity(
+            Identity::from_pem(&identity)
+                .map_err(|err| format!("invalid client certificate: {err}"))?,
+        );
+    }
+
+    let client = client_builder.build().map_err(|err| err.to_string())?;
+    let mut clients = clients.lock().unwrap();
+    clients.retain(|options, _| !options.is_outdated_by(&client_options));
+    clients.insert(client_options, client.clone());
+    Ok(client)
+}
+
+/// State and message of a failed check.
+type Failure = (CheckState, Option<String>);
+
+fn down(err: impl ToString) -> Failure {
+    (CheckState::Down, Some(err.to_string()))
+}
+
+fn warn(err: impl ToString) -> Failure {
+    (CheckState::Warn, Some(err.to_string()))
+}
+
+/// Returns given value as NUL terminated string, as used by database protocols.
+fn nul_terminated(value: &str) -> Vec<u8> {
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.push(0);
+    bytes
+}
+
+/// Returns host of given URL. IPv6 addresses are enclosed in brackets within URLs,
+/// which are removed to connect to them.
+fn url_host(url: &Url) -> Option<&str> {
+    url.host_str()
+        .map(|host| host.trim_start_matches('[').trim_end_matches(']'))
+}
+
+const CERTIFICATE_ERROR: &str = "certificate error";
+const TLS_ERROR: &str = "TLS error";
+
+/// Returns the reason of an error if caused by TLS, e.g. an untrusted or expired certificate.
+fn tls_failure_reason(err: &(dyn Error + 'static)) -> Option<String> {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(tls_error) = err.downcast_ref::<rustls::Error>() {
+            return Some(match tls_error {
+                rustls::Error::InvalidCertificate(_)
+                | rustls::Error::NoCertificatesPresented
+                | rustls::Error::AlertReceived(
+                    AlertDescription::BadCertificate
+                    | AlertDescription::UnsupportedCertificate
+                    | AlertDescription::CertificateRevoked
+                    | AlertDescription::CertificateExpired
+                    | AlertDescription::CertificateUnknown
+                    | AlertDescription::UnknownCA
+                    | AlertDescription::CertificateRequired,
+                ) => format!("{CERTIFICATE_ERROR}: {tls_error}"),
+                _ => format!("{TLS_ERROR}: {tls_error}"),
+            });
+        }
+        // TLS errors are wrapped into I/O errors, which do not expose them as source
+        source = match err
+            .downcast_ref::<io::Error>()
+            .and_then(|err| err.get_ref())
+        {
+            Some(inner) => Some(inner),
+            None => err.source(),
+        };
+    }
+    None
 }
 
 pub trait HttpBasedChecker {
     async fn check(&self) -> CheckResult {
-        CheckResult {
-            name: self.get_check_config().name.to_string(),
-            state: match reqwest::get(self.get_check_config().url.as_str()).await {
-                Ok(r) => Self::check_response(r).await,
-                Err(_) => CheckState::Down,
-            },
+        let check_config = self.get_check_config();
+        let client = match http_client(check_config).await {
+            Ok(client) => client,
+            Err(err) => {
+                let mut check_result =
+                    CheckResult::new(check_config, CheckState::Down, Instant::now());
+                check_result.message = Some(err);
+                return check_result;
+            }
+        };
+
+        let mut request_builder = client.request(
+            check_config.method.clone().unwrap_or(Method::GET),
+            check_config.url.as_str(),
+        );
+        for (name, value) in &check_config.headers {
+            request_builder = request_builder.header(name, value);
+        }
+        if let Some(basic_auth) = &check_config.basic_auth {
+            request_builder =
+                request_builder.basic_auth(&basic_auth.username, basic_auth.password.as_ref());
+        }
+        if let Some(bearer_token) = &check_config.bearer_token {
+            request_builder = request_builder.bearer_auth(bearer_token);
         }
+        if let Some(body) = &check_config.body {
+            request_builder = request_builder.body(body.to_string());
+        }
+
+        // Building the client is not part of the latency, only sending the request is
+        let started = Instant::now();
+        let request = async {
+            match request_builder.send().await {
+                Ok(r) => (self.check_response(r).await, None),
+                Err(err) => (CheckState::Down, tls_failure_reason(&err)),
+            }
+        };
+
+        let (state, message) = timeout(self.get_timeout(), request)
+            .await
+            .unwrap_or((CheckState::Timeout, None));
+        let mut check_result = CheckResult::new(check_config, state, started);
+        check_result.message = message;
+        check_result
     }
 
-    async fn check_response(response: Response) -> CheckState;
+    async fn check_response(&self, response: Response) -> CheckState;
 
     fn get_check_config(&self) -> &CheckConfig;
+
+    fn get_timeout(&self) -> Duration;
+}
+
+/// Returns configuration of a check named `test` using given check type and URL.
+#[cfg(test)]
+pub(crate) fn test_check_config(check_type: CheckType, url: &str) -> CheckConfig {
+    toml::from_str(&format!(
+        "name = \"test\"\nurl = \"{url}\"\ncheck_type = \"{check_type}\""
+    ))
+    .unwrap()
+}
+
+/// Accepts a single TCP connection and passes it to given handler. Returns address to connect to
+/// and handle to receive the result of the handler, e.g. everything sent by client.
+#[cfg(test)]
+pub(crate) async fn accept_once<F, T>(
+    handler: impl FnOnce(tokio::net::TcpStream) -> F + Send + 'static,
+) -> (String, tokio::task::JoinHandle<T>)
+where
+    F: std::future::Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let handle = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        handler(stream).await
+    });
+    (address, handle)
+}
+
+/// Answers a single UDP datagram using the reply returned by given function. Returns address to send to
+/// and handle to receive the datagram as sent by client.
+#[cfg(test)]
+pub(crate) async fn answer_datagram(
+    reply: impl FnOnce(&[u8]) -> Vec<u8> + Send + 'static,
+) -> (String, tokio::task::JoinHandle<Vec<u8>>) {
+    let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let address = socket.local_addr().unwrap().to_string();
+    let handle = tokio::spawn(async move {
+        let mut buffer = [0; 512];
+        let (len, client) = socket.recv_from(&mut buffer).await.unwrap();
+        socket
+            .send_to(&reply(&buffer[..len]), client)
+            .await
+            .unwrap();
+        buffer[..len].to_vec()
+    });
+    (address, handle)
+}
+
+/// Serves a single HTTP request using given response and returns URL to be requested
+/// and handle to receive the request as sent by client.
+#[cfg(test)]
+pub(crate) async fn serve_once(
+    response: &'static str,
+) -> (String, tokio::task::JoinHandle<String>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (address, handle) = accept_once(move |mut stream| async move {
+        let mut request = vec![];
+        let mut buffer = [0; 1024];
+        // Read until end of headers and complete body, if any
+        loop {
+            let len = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..len]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some(header_end) = text.find("\r\n\r\n") {
+                let content_length = text
+                    .lines()
+                    .find_map(|line| {
+                        line.to_lowercase()
+                            .strip_prefix("content-length:")
+                            .map(|value| value.trim().parse::<usize>().unwrap_or(0))
+                    })
+                    .unwrap_or(0);
+                if request.len() >= header_end + 4 + content_length {
+                    break;
+                }
+            }
+            if len == 0 {
+                break;
+            }
+        }
+        stream.write_all(response.as_bytes()).await.unwrap();
+        let _ = stream.shutdown().await;
+        String::from_utf8_lossy(&request).to_string()
+    })
+    .await;
+    (format!("http://{address}"), handle)
+}
+
+/// Returns TLS acceptor using given test certificate in `tests/tls`, e.g. `server`, and ALPN protocols.
+/// If `client_auth` is set, clients must present a certificate issued by the test CA.
+#[cfg(test)]
+pub(crate) fn tls_acceptor(
+    certificate: &str,
+    client_auth: bool,
+    alpn_protocols: Vec<Vec<u8>>,
+) -> tokio_rustls::TlsAcceptor {
+    use rustls::server::WebPkiClientVerifier;
+    use rustls::{RootCertStore, ServerConfig};
+    use std::sync::Arc;
+
+    let read_pem = |filename: &str| std::fs::read(filename).unwrap();
+    let certificates =
+        rustls_pemfile::certs(&mut read_pem(&format!("tests/tls/{certificate}.pem")).as_slice())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+    let key = rustls_pemfile::private_key(
+        &mut read_pem(&format!("tests/tls/{certificate}.key")).as_slice(),
+    )
+    .unwrap()
+    .unwrap();
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let server_config = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .unwrap();
+    let server_config = if client_auth {
+        let mut roots = RootCertStore::empty();
+        for certificate in rustls_pemfile::certs(&mut read_pem("tests/tls/ca.pem").as_slice()) {
+            roots.add(certificate.unwrap()).unwrap();
+        }
+        server_config.with_client_cert_verifier(
+            WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .unwrap(),
+        )
+    } else {
+        server_config.with_no_client_auth()
+    };
+    let mut server_config = server_config.with_single_cert(certificates, key).unwrap();
+    server_config.alpn_protocols = alpn_protocols;
+    tokio_rustls::TlsAcceptor::from(Arc::new(server_config))
+}
+
+/// Serves HTTPS requests using given test certificate in `tests/tls`, e.g. `server`, and returns URL
+/// to be requested. If `client_auth` is set, clients must present a certificate issued by the test CA.
+#[cfg(test)]
+pub(crate) async fn serve_tls(certificate: &str, client_auth: bool) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let acceptor = tls_acceptor(certificate, client_auth, vec![]);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("https://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                let Ok(mut stream) = acceptor.accept(stream).await else {
+                    return;
+                };
+                let mut buffer = [0; 1024];
+                while let Ok(len) = stream.read(&mut buffer).await {
+                    if len == 0 {
+                        break;
+                    }
+                    let response = "HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n";
+                    let _ = stream.write_all(response.as_bytes()).await;
+                }
+            });
+        }
+    });
+    url
 }
 
 #[cfg(test)]
 mod tests {
     use crate::checker::*;
 
+    #[test]
+    fn test_should_display_check_result_without_color() {
+        let check_result = CheckResult {
+            name: "test".to_string(),
+            state: CheckState::Up,
+            latency: None,
+            message: None,
+        };
+
+        assert_eq!(
+            ToNonTerminalString::to_string(&check_result, None),
+            r##"{"full_text":"test","name":"test","separator_block_width":16}"##
+        )
+    }
+
+    #[test]
+    fn test_should_display_check_result_as_json() {
+        let check_result = CheckResult {
+            name: "test".to_string(),
+            state: CheckState::Timeout,
+            latency: None,
+            message: None,
+        };
+
+        assert_eq!(
+            ToJsonString::to_string(&check_result),
+            r##"{"name":"test","state":"Timeout"}"##
+        )
+    }
+
+    #[test]
+    fn test_should_display_check_result_with_latency_as_json() {
+        let check_result = CheckResult {
+            name: "test".to_string(),
+            state: CheckState::Up,
+            latency: Some(Duration::from_millis(123)),
+            message: None,
+        };
+
+        assert_eq!(
+            ToJsonString::to_string(&check_result),
+            r##"{"latency_ms":123,"name":"test","state":"Up"}"##
+        )
+    }
+
+    #[test]
+    fn test_should_display_check_result_with_message_as_json() {
+        let check_result = CheckResult {
+            name: "test".to_string(),
+            state: CheckState::Down,
+            latency: None,
+            message: Some("certificate error: expired".to_string()),
+        };
+
+        assert_eq!(
+            ToJsonString::to_string(&check_result),
+            r##"{"message":"certificate error: expired","name":"test","state":"Down"}"##
+        )
+    }
+
+    #[test]
+    fn test_should_degrade_state_on_latency_thresholds() {
+        let check_config: CheckConfig = toml::from_str(
+            r#"
+                name = "test"
+                url = "http://localhost"
+                warn_latency = 1
+                down_latency = 2
+            "#,
+        )
+        .unwrap();
+        let now = Instant::now();
+
+        let check_result = CheckResult::new(&check_config, CheckState::Up, now);
+        assert_eq!(check_result.state, CheckState::Up);
+        assert!(check_result.latency.is_some());
+
+        let check_result =
+            CheckResult::new(&check_config, CheckState::Up, now - Duration::from_secs(1));
+        assert_eq!(check_result.state, CheckState::Warn);
+
+        let check_result = CheckResult::new(
+            &check_config,
+            CheckState::Warn,
+            now - Duration::from_secs(2),
+        );
+        assert_eq!(check_result.state, CheckState::Down);
+
+        let check_result = CheckResult::new(
+            &check_config,
+            CheckState::Timeout,
+            now - Duration::from_secs(2),
+        );
+        assert_eq!(check_result.state, CheckState::Timeout);
+        assert_eq!(check_result.latency, None);
+    }
+
+    #[test]
+    fn test_should_return_exit_code_of_check_state() {
+        assert_eq!(CheckState::Up.exit_code(), 0);
+        assert_eq!(CheckState::Warn.exit_code(), 1);
+        assert_eq!(CheckState::Down.exit_code(), 2);
+        assert_eq!(CheckState::Timeout.exit_code(), 2);
+        assert_eq!(CheckState::Unknown.exit_code(), 3);
+    }
+
     #[test]
     fn test_should_display_check_result_up_in_term() {
         let check_result = CheckResult {
             name: "test".to_string(),
             state: CheckState::Up,
+            latency: None,
+            message: None,
         };
 
         assert_eq!(ToNonColoredTerminalString::to_string(&check_result), "test")
@@ -134,6 +760,8 @@ mod tests {
         let check_result = CheckResult {
             name: "test".to_string(),
             state: CheckState::Warn,
+            latency: None,
+            message: None,
         };
 
         assert_eq!(ToNonColoredTerminalString::to_string(&check_result), "test")
@@ -144,6 +772,8 @@ mod tests {
         let check_result = CheckResult {
             name: "test".to_string(),
             state: CheckState::Down,
+            latency: None,
+            message: None,
         };
 
         assert_eq!(ToNonColoredTerminalString::to_string(&check_result), "test")
@@ -154,6 +784,8 @@ mod tests {
         let check_result = CheckResult {
             name: "test".to_string(),
             state: CheckState::Up,
+            latency: None,
+            message: None,
         };
 
         assert_eq!(
@@ -167,6 +799,8 @@ mod tests {
         let check_result = CheckResult {
             name: "test".to_string(),
             state: CheckState::Warn,
+            latency: None,
+            message: None,
         };
 
         assert_eq!(
@@ -180,6 +814,8 @@ mod tests {
         let check_result = CheckResult {
             name: "test".to_string(),
             state: CheckState::Down,
+            latency: None,
+            message: None,
         };
 
         assert_eq!(
@@ -188,15 +824,32 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_should_display_check_result_timeout_in_colored_term() {
+        let check_result = CheckResult {
+            name: "test".to_string(),
+            state: CheckState::Timeout,
+            latency: None,
+            message: None,
+        };
+
+        assert_eq!(
+            ToColoredTerminalString::to_string(&check_result),
+            "\u{1b}[35mtest\u{1b}[0m"
+        )
+    }
+
     #[test]
     fn test_should_display_check_result_up() {
         let check_result = CheckResult {
             name: "test".to_string(),
             state: CheckState::Up,
+            latency: None,
+            message: None,
         };
 
         assert_eq!(
-            ToNonTerminalString::to_string(&check_result),
+            ToNonTerminalString::to_string(&check_result, Some(&ColorConfig::default())),
             r##"{"color":"#00FF00","full_text":"test","name":"test","separator_block_width":16}"##
         )
     }
@@ -206,10 +859,12 @@ mod tests {
         let check_result = CheckResult {
             name: "test".to_string(),
             state: CheckState::Warn,
+            latency: None,
+            message: None,
         };
 
         assert_eq!(
-            ToNonTerminalString::to_string(&check_result),
+            ToNonTerminalString::to_string(&check_result, Some(&ColorConfig::default())),
             r##"{"color":"#FFFF00","full_text":"test","name":"test","separator_block_width":16}"##
         )
     }
@@ -219,11 +874,116 @@ mod tests {
         let check_result = CheckResult {
             name: "test".to_string(),
             state: CheckState::Down,
+            latency: None,
+            message: None,
         };
 
         assert_eq!(
-            ToNonTerminalString::to_string(&check_result),
+            ToNonTerminalString::to_string(&check_result, Some(&ColorConfig::default())),
             r##"{"color":"#FF0000","full_text":"test","name":"test","separator_block_width":16}"##
         )
     }
+
+    #[test]
+    fn test_should_display_check_result_down_on_tls_failure() {
+        let check_result = CheckResult {
+            name: "test".to_string(),
+            state: CheckState::Down,
+            latency: None,
+            message: Some("certificate error: invalid peer certificate: Expired".to_string()),
+        };
+
+        assert_eq!(
+            ToNonTerminalString::to_string(&check_result, Some(&ColorConfig::default())),
+            r##"{"color":"#FF0000","full_text":"test (certificate error)","name":"test","separator_block_width":16,"short_text":"test"}"##
+        );
+        assert_eq!(
+            ToNonColoredTerminalString::to_string(&check_result),
+            "test (certificate error)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_replace_http_client_if_file_changed() {
+        let dir = std::env::temp_dir().join(format!("checkbar-{}-clients", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ca_file = dir.join("ca.pem");
+        std::fs::copy("tests/tls/ca.pem", &ca_file).unwrap();
+        let check_config: CheckConfig = toml::from_str(&format!(
+            r#"
+                name = "test"
+                url = "https://localhost"
+                ca_file = "{}"
+            "#,
+            ca_file.display()
+        ))
+        .unwrap();
+        let count = |path: &str| {
+            HTTP_CLIENTS
+                .get()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .keys()
+                .filter(|options| {
+                    options
+                        .ca_file
+                        .as_ref()
+                        .is_some_and(|file| file.path == path)
+                })
+                .count()
+        };
+        let path = ca_file.to_string_lossy().to_string();
+
+        http_client(&check_config).await.unwrap();
+        let outdated = ClientOptions::of(&check_config).await;
+        assert_eq!(count(&path), 1);
+
+        let file = std::fs::File::options()
+            .append(true)
+            .open(&ca_file)
+            .unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        http_client(&check_config).await.unwrap();
+        assert_eq!(count(&path), 1);
+        assert!(!HTTP_CLIENTS
+            .get()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .contains_key(&outdated));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_should_display_check_result_timeout() {
+        let check_result = CheckResult {
+            name: "test".to_string(),
+            state: CheckState::Timeout,
+            latency: None,
+            message: None,
+        };
+
+        assert_eq!(
+            ToNonTerminalString::to_string(&check_result, Some(&ColorConfig::default())),
+            r##"{"color":"#FF8000","full_text":"test","name":"test","separator_block_width":16}"##
+        )
+    }
+
+    #[test]
+    fn test_should_display_check_result_unknown() {
+        let check_result = CheckResult {
+            name: "test".to_string(),
+            state: CheckState::Unknown,
+            latency: None,
+            message: None,
+        };
+
+        assert_eq!(
+            ToNonTerminalString::to_string(&check_result, Some(&ColorConfig::default())),
+            r##"{"color":"#808080","full_text":"test","name":"test","separator_block_width":16}"##
+        )
+    }
 }
@@ -0,0 +1,452 @@
+use std::io;
+use std::time::Duration;
+
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+use ring::digest::{Context, SHA256};
+use rsa::pkcs8::DecodePublicKey;
+use rsa::rand_core::OsRng;
+use rsa::{Oaep, RsaPublicKey};
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Instant};
+
+use crate::checker::{down, nul_terminated, url_host, warn, CheckResult, CheckState, Failure};
+use crate::config::CheckConfig;
+
+const CLIENT_LONG_PASSWORD: u32 = 0x0000_0001;
+const CLIENT_CONNECT_WITH_DB: u32 = 0x0000_0008;
+const CLIENT_PROTOCOL_41: u32 = 0x0000_0200;
+const CLIENT_SECURE_CONNECTION: u32 = 0x0000_8000;
+const CLIENT_PLUGIN_AUTH: u32 = 0x0008_0000;
+const MAX_PACKET_LEN: u32 = 1 << 24;
+const CHARSET_UTF8MB4: u8 = 45;
+const COM_QUIT: u8 = 0x01;
+const COM_QUERY: u8 = 0x03;
+const REQUEST_PUBLIC_KEY: u8 = 0x02;
+
+pub struct Checker<'a> {
+    check_config: &'a CheckConfig,
+    timeout: Duration,
+}
+
+impl Checker<'_> {
+    pub fn new(check_config: &CheckConfig, timeout: Duration) -> Checker<'_> {
+        Checker {
+            check_config,
+            timeout,
+        }
+    }
+
+    pub async fn check(&self) -> CheckResult {
+        let started = Instant::now();
+        let (state, message) = match timeout(self.timeout, self.query()).await {
+            Ok(Ok(())) => (CheckState::Up, None),
+            Ok(Err(failure)) => failure,
+            Err(_) => (CheckState::Timeout, None),
+        };
+
+        let mut check_result = CheckResult::new(self.check_config, state, started);
+        check_result.message = message;
+        check_result
+    }
+
+    /// Authenticates using credentials of check URL and runs configured query.
+    /// Without username, only the server's greeting is checked.
+    async fn query(&self) -> Result<(), Failure> {
+        let url = Url::parse(&self.check_config.url).map_err(down)?;
+        let host = url_host(&url).ok_or_else(|| down("URL must contain host"))?;
+        let tcp_stream = TcpStream::connect((host, url.port().unwrap_or(3306)))
+            .await
+            .map_err(down)?;
+        let mut connection = Connection {
+            stream: tcp_stream,
+            sequence: 0,
+        };
+
+        let greeting = connection.receive().await.map_err(down)?;
+        let (nonce, plugin) = match greeting.first() {
+            Some(10) => parse_greeting(&greeting).ok_or_else(|| down("invalid greeting"))?,
+            // Server refuses connection, e.g. if host is blocked
+            Some(0xff) => return Err(warn(error_message(&greeting))),
+            _ => return Err(down("unexpected greeting")),
+        };
+
+        let user = percent_decode_str(url.username()).decode_utf8_lossy();
+        if user.is_empty() {
+            return Ok(());
+        }
+        let password = url
+            .password()
+            .map(|password| percent_decode_str(password).decode_utf8_lossy())
+            .unwrap_or_default();
+        let database = percent_decode_str(url.path().trim_start_matches('/')).decode_utf8_lossy();
+
+        let mut capabilities = CLIENT_LONG_PASSWORD
+            | CLIENT_PROTOCOL_41
+            | CLIENT_SECURE_CONNECTION
+            | CLIENT_PLUGIN_AUTH;
+        if !database.is_empty() {
+            capabilities |= CLIENT_CONNECT_WITH_DB;
+        }
+        let scramble = scramble(&plugin, &password, &nonce)?;
+        let mut response = capabilities.to_le_bytes().to_vec();
+        response.extend(MAX_PACKET_LEN.to_le_bytes());
+        response.push(CHARSET_UTF8MB4);
+        response.extend([0; 23]);
+        response.extend(nul_terminated(&user));
+        response.push(scramble.len() as u8);
+        response.extend(scramble);
+        if !database.is_empty() {
+            response.extend(nul_terminated(&database));
+        }
+        response.extend(nul_terminated(&plugin));
+        connection.send(&response).await.map_err(down)?;
+        connection.authenticate(&password, &nonce).await?;
+
+        let mut command = vec![COM_QUERY];
+        command.extend(self.check_config.query.as_bytes());
+        connection.sequence = 0;
+        connection.send(&command).await.map_err(down)?;
+        let reply = connection.receive().await.map_err(down)?;
+        let result = match reply.first() {
+            Some(0xff) => Err(warn(error_message(&reply))),
+            Some(_) => Ok(()),
+            None => Err(down("unexpected reply")),
+        };
+        connection.sequence = 0;
+        let _ = connection.send(&[COM_QUIT]).await;
+        result
+    }
+}
+
+struct Connection {
+    stream: TcpStream,
+    sequence: u8,
+}
+
+impl Connection {
+    async fn send(&mut self, payload: &[u8]) -> io::Result<()> {
+        let mut packet = (payload.len() as u32).to_le_bytes();
+        packet[3] = self.sequence;
+        self.sequence = self.sequence.wrapping_add(1);
+        self.stream.write_all(&packet).await?;
+        self.stream.write_all(payload).await
+    }
+
+    async fn receive(&mut self) -> io::Result<Vec<u8>> {
+        let mut header = [0; 4];
+        self.stream.read_exact(&mut header).await?;
+        self.sequence = header[3].wrapping_add(1);
+        let len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+        let mut payload = vec![0; len];
+        self.stream.read_exact(&mut payload).await?;
+        Ok(payload)
+    }
+
+    /// Answers authentication method switches and fast authentication until authenticated. Full authentication
+    /// of `caching_sha2_password` sends the password encrypted using the server's RSA public key.
+    /// Error packets, e.g. for denied access, result in `Warn`.
+    async fn authenticate(&mut self, password: &str, nonce: &[u8]) -> Result<(), Failure> {
+        let mut nonce = nonce.to_vec();
+        loop {
+            let reply = self.receive().await.map_err(down)?;
+            match reply.first() {
+                Some(0x00) => return Ok(()),
+                Some(0xff) => return Err(warn(error_message(&reply))),
+                Some(0xfe) => {
+                    let mut parts = reply[1..].splitn(2, |b| *b == 0);
+                    let plugin = String::from_utf8_lossy(parts.next().unwrap_or_default());
+                    let next_nonce = parts.next().unwrap_or_default();
+                    nonce = next_nonce.strip_suffix(&[0]).unwrap_or(next_nonce).to_vec();
+                    let scramble = scramble(&plugin, password, &nonce)?;
+                    self.send(&scramble).await.map_err(down)?;
+                }
+                // Fast authentication succeeded, OK packet follows
+                Some(0x01) if reply.get(1) == Some(&0x03) => {}
+                // Full authentication required, request public key of server
+                Some(0x01) if reply.get(1) == Some(&0x04) => {
+                    self.send(&[REQUEST_PUBLIC_KEY]).await.map_err(down)?;
+                }
+                Some(0x01) => {
+                    let public_key = String::from_utf8_lossy(&reply[1..]);
+                    let encrypted = encrypt_password(password, &nonce, &public_key)?;
+                    self.send(&encrypted).await.map_err(down)?;
+                }
+                _ => return Err(down("unexpected reply")),
+            }
+        }
+    }
+}
+
+/// Encrypts NUL terminated password, XORed with nonce, using RSA public key of server in PEM format.
+fn encrypt_password(password: &str, nonce: &[u8], public_key: &str) -> Result<Vec<u8>, Failure> {
+    let public_key = RsaPublicKey::from_public_key_pem(public_key)
+        .map_err(|err| down(format!("invalid public key: {err}")))?;
+    let password = nul_terminated(password)
+        .iter()
+        .zip(nonce.iter().cycle())
+        .map(|(a, b)| a ^ b)
+        .collect::<Vec<_>>();
+    public_key
+        .encrypt(&mut OsRng, Oaep::new::<Sha1>(), &password)
+        .map_err(|err| down(format!("cannot encrypt password: {err}")))
+}
+
+/// Returns nonce and authentication plugin name of protocol version 10 greeting.
+fn parse_greeting(greeting: &[u8]) -> Option<(Vec<u8>, String)> {
+    let version_len = greeting.iter().skip(1).position(|b| *b == 0)?;
+    // Skip protocol version, server version and connection ID
+    let rest = greeting.get(version_len + 6..)?;
+    let mut nonce = rest.get(..8)?.to_vec();
+    let nonce_len = *rest.get(16)? as usize;
+    let rest = rest.get(27..)?;
+    let second_part_len = nonce_len.saturating_sub(8).max(13);
+    let second_part = rest.get(..second_part_len)?;
+    nonce.extend(second_part.strip_suffix(&[0]).unwrap_or(second_part));
+    let plugin = rest.get(second_part_len..)?.split(|b| *b == 0).next()?;
+    Some((nonce, String::from_utf8_lossy(plugin).to_string()))
+}
+
+/// Returns the message of an error packet, skipping error code and SQL state.
+fn error_message(packet: &[u8]) -> String {
+    let message = match packet.get(3) {
+        Some(b'#') => packet.get(9..),
+        _ => packet.get(3..),
+    };
+    String::from_utf8_lossy(message.unwrap_or_default()).to_string()
+}
+
+/// Scrambles password with server nonce as required by authentication plugin.
+fn scramble(plugin: &str, password: &str, nonce: &[u8]) -> Result<Vec<u8>, Failure> {
+    if password.is_empty() {
+        return Ok(Vec::new());
+    }
+    let sha256 = |parts: &[&[u8]]| {
+        let mut context = Context::new(&SHA256);
+        parts.iter().for_each(|part| context.update(part));
+        context.finish()
+    };
+    let xor = |a: &[u8], b: &[u8]| a.iter().zip(b).map(|(a, b)| a ^ b).collect();
+
+    match plugin {
+        // SHA1(password) XOR SHA1(nonce + SHA1(SHA1(password)))
+        "mysql_native_password" => {
+            let hashed = Sha1::digest(password);
+            let double_hashed = Sha1::digest(hashed);
+            let salted = Sha1::new()
+                .chain_update(nonce)
+                .chain_update(double_hashed)
+                .finalize();
+            Ok(xor(&hashed, &salted))
+        }
+        // SHA256(password) XOR SHA256(SHA256(SHA256(password)) + nonce)
+        "caching_sha2_password" => {
+            let hashed = sha256(&[password.as_bytes()]);
+            let double_hashed = sha256(&[hashed.as_ref()]);
+            let salted = sha256(&[double_hashed.as_ref(), nonce]);
+            Ok(xor(hashed.as_ref(), salted.as_ref()))
+        }
+        _ => Err(warn(format!(
+            "unsupported authentication plugin '{plugin}'"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::{Oaep, RsaPrivateKey};
+    use sha1::{Digest, Sha1};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::task::JoinHandle;
+
+    use crate::checker::mysql::{scramble, Checker};
+    use crate::checker::{accept_once, test_check_config, CheckState};
+    use crate::config::CheckType;
+
+    const NONCE: &[u8] = b"abcdefghijklmnopqrst";
+
+    fn greeting(plugin: &str) -> Vec<u8> {
+        let mut greeting = vec![10];
+        greeting.extend(b"8.0.36\0");
+        greeting.extend(1u32.to_le_bytes());
+        greeting.extend(&NONCE[..8]);
+        greeting.extend([0, 0xff, 0xff, 45, 2, 0, 0xff, 0xff, 21]);
+        greeting.extend([0; 10]);
+        greeting.extend(&NONCE[8..]);
+        greeting.push(0);
+        greeting.extend(plugin.as_bytes());
+        greeting.push(0);
+        greeting
+    }
+
+    /// Accepts a single connection, sends greeting and answers each packet using given replies,
+    /// returns address and handle to receive all packets as sent by client.
+    async fn serve_mysql(replies: Vec<Vec<u8>>) -> (String, JoinHandle<Vec<Vec<u8>>>) {
+        accept_once(move |mut stream| async move {
+            let mut received = Vec::new();
+            let mut sequence = 0;
+            for reply in replies {
+                if sequence > 0 {
+                    let mut header = [0; 4];
+                    if stream.read_exact(&mut header).await.is_err() {
+                        break;
+                    }
+                    let len = u32::from_le_bytes([header[0], header[1], header[2], 0]);
+                    let mut payload = vec![0; len as usize];
+                    stream.read_exact(&mut payload).await.unwrap();
+                    received.push(payload);
+                    sequence = header[3] + 1;
+                }
+                let mut header = (reply.len() as u32).to_le_bytes();
+                header[3] = sequence;
+                stream.write_all(&header).await.unwrap();
+                stream.write_all(&reply).await.unwrap();
+                sequence += 1;
+            }
+            received
+        })
+        .await
+    }
+
+    #[test]
+    fn test_should_scramble_native_password() {
+        let scrambled = scramble("mysql_native_password", "secret", NONCE).unwrap();
+
+        // Server verifies scramble using stored SHA1(SHA1(password))
+        let stored = Sha1::digest(Sha1::digest(b"secret"));
+        let mut salted = NONCE.to_vec();
+        salted.extend(stored);
+        let salted = Sha1::digest(&salted);
+        let candidate = scrambled
+            .iter()
+            .zip(salted)
+            .map(|(a, b)| a ^ b)
+            .collect::<Vec<_>>();
+        assert_eq!(Sha1::digest(&candidate), stored);
+    }
+
+    #[tokio::test]
+    async fn test_should_return_up_state() {
+        let (address, received) = serve_mysql(vec![
+            greeting("caching_sha2_password"),
+            vec![0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00],
+            vec![0x01],
+        ])
+        .await;
+        let check_config = test_check_config(
+            CheckType::Mysql,
+            &format!("mysql://test:secret@{address}/app"),
+        );
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+        let received = received.await.unwrap();
+        assert!(received[0].windows(5).any(|window| window == b"test\0"));
+        assert!(received[0].ends_with(b"app\0caching_sha2_password\0"));
+        assert_eq!(received[1], b"\x03SELECT 1");
+    }
+
+    #[tokio::test]
+    async fn test_should_encrypt_password_on_full_authentication() {
+        let mut public_key = vec![0x01];
+        public_key.extend(fs::read("tests/mysql/server.pub").unwrap());
+        let (address, received) = serve_mysql(vec![
+            greeting("caching_sha2_password"),
+            vec![0x01, 0x04],
+            public_key,
+            vec![0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00],
+            vec![0x01],
+        ])
+        .await;
+        let check_config =
+            test_check_config(CheckType::Mysql, &format!("mysql://test:secret@{address}"));
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+        let received = received.await.unwrap();
+        assert_eq!(received[1], [0x02]);
+        let private_key = RsaPrivateKey::read_pkcs8_pem_file("tests/mysql/server.key").unwrap();
+        let password = private_key
+            .decrypt(Oaep::new::<Sha1>(), &received[2])
+            .unwrap()
+            .iter()
+            .zip(NONCE.iter().cycle())
+            .map(|(a, b)| a ^ b)
+            .collect::<Vec<_>>();
+        assert_eq!(password, b"secret\0");
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_on_denied_access() {
+        let mut error = vec![0xff, 0x15, 0x04];
+        error.extend(b"#28000Access denied for user 'test'@'localhost' (using password: YES)");
+        let (address, _) = serve_mysql(vec![greeting("mysql_native_password"), error]).await;
+
+        let check_result = Checker::new(
+            &test_check_config(CheckType::Mysql, &format!("mysql://test:wrong@{address}")),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Warn);
+        assert_eq!(
+            check_result.message,
+            Some("Access denied for user 'test'@'localhost' (using password: YES)".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_on_failed_query() {
+        let mut error = vec![0xff, 0x7a, 0x04];
+        error.extend(b"#42S02Table 'app.health' doesn't exist");
+        let (address, _) = serve_mysql(vec![
+            greeting("mysql_native_password"),
+            vec![0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00],
+            error,
+        ])
+        .await;
+
+        let check_result = Checker::new(
+            &test_check_config(
+                CheckType::Mysql,
+                &format!("mysql://test:secret@{address}/app"),
+            ),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Warn);
+        assert_eq!(
+            check_result.message,
+            Some("Table 'app.health' doesn't exist".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_return_up_state_on_greeting_without_user() {
+        let (address, _) = serve_mysql(vec![greeting("mysql_native_password")]).await;
+
+        let check_result = Checker::new(
+            &test_check_config(CheckType::Mysql, &format!("mysql://{address}")),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+    }
+}
@@ -0,0 +1,469 @@
+use std::io;
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use md5::{Digest, Md5};
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{digest, hmac, pbkdf2};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Instant};
+
+use crate::checker::{down, nul_terminated, url_host, warn, CheckResult, CheckState, Failure};
+use crate::config::CheckConfig;
+
+const PROTOCOL_VERSION: u32 = 196608;
+const SSL_REQUEST: [u8; 8] = [0, 0, 0, 8, 0x04, 0xd2, 0x16, 0x2f];
+const MAX_MESSAGE_LEN: usize = 1 << 20;
+
+pub struct Checker<'a> {
+    check_config: &'a CheckConfig,
+    timeout: Duration,
+}
+
+impl Checker<'_> {
+    pub fn new(check_config: &CheckConfig, timeout: Duration) -> Checker<'_> {
+        Checker {
+            check_config,
+            timeout,
+        }
+    }
+
+    pub async fn check(&self) -> CheckResult {
+        let started = Instant::now();
+        let (state, message) = match timeout(self.timeout, self.query()).await {
+            Ok(Ok(())) => (CheckState::Up, None),
+            Ok(Err(failure)) => failure,
+            Err(_) => (CheckState::Timeout, None),
+        };
+
+        let mut check_result = CheckResult::new(self.check_config, state, started);
+        check_result.message = message;
+        check_result
+    }
+
+    /// Authenticates using credentials of check URL and runs configured query.
+    /// Without username, only the server's answer to an SSL request is checked.
+    async fn query(&self) -> Result<(), Failure> {
+        let url = Url::parse(&self.check_config.url).map_err(down)?;
+        let host = url_host(&url).ok_or_else(|| down("URL must contain host"))?;
+        let tcp_stream = TcpStream::connect((host, url.port().unwrap_or(5432)))
+            .await
+            .map_err(down)?;
+        let mut connection = Connection(tcp_stream);
+
+        let user = percent_decode_str(url.username()).decode_utf8_lossy();
+        if user.is_empty() {
+            connection.0.write_all(&SSL_REQUEST).await.map_err(down)?;
+            let mut reply = [0];
+            connection.0.read_exact(&mut reply).await.map_err(down)?;
+            return match reply[0] {
+                b'S' | b'N' => Ok(()),
+                _ => Err(down("unexpected reply to SSL request")),
+            };
+        }
+        let password = url
+            .password()
+            .map(|password| percent_decode_str(password).decode_utf8_lossy());
+        let database = percent_decode_str(url.path().trim_start_matches('/')).decode_utf8_lossy();
+
+        connection.startup(&user, &database).await.map_err(down)?;
+        connection.authenticate(&user, password.as_deref()).await?;
+        connection.wait_until_ready().await?;
+        connection
+            .send(b'Q', &nul_terminated(&self.check_config.query))
+            .await
+            .map_err(down)?;
+        let result = connection.wait_until_ready().await;
+        let _ = connection.send(b'X', &[]).await;
+        result
+    }
+}
+
+struct Connection(TcpStream);
+
+impl Connection {
+    async fn startup(&mut self, user: &str, database: &str) -> io::Result<()> {
+        let mut body = PROTOCOL_VERSION.to_be_bytes().to_vec();
+        for (name, value) in [
+            ("user", user),
+            ("database", database),
+            ("application_name", "checkbar"),
+        ] {
+            if !value.is_empty() {
+                body.extend(nul_terminated(name));
+                body.extend(nul_terminated(value));
+            }
+        }
+        body.push(0);
+
+        let mut message = ((body.len() + 4) as u32).to_be_bytes().to_vec();
+        message.extend(body);
+        self.0.write_all(&message).await
+    }
+
+    async fn send(&mut self, tag: u8, body: &[u8]) -> io::Result<()> {
+        let mut message = vec![tag];
+        message.extend(((body.len() + 4) as u32).to_be_bytes());
+        message.extend(body);
+        self.0.write_all(&message).await
+    }
+
+    async fn receive(&mut self) -> io::Result<(u8, Vec<u8>)> {
+        let mut header = [0; 5];
+        self.0.read_exact(&mut header).await?;
+        let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        if !(4..=MAX_MESSAGE_LEN).contains(&len) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid message length",
+            ));
+        }
+        let mut body = vec![0; len - 4];
+        self.0.read_exact(&mut body).await?;
+        Ok((header[0], body))
+    }
+
+    /// Receives authentication request and returns its code and data.
+    /// Error responses, e.g. for wrong passwords, result in `Warn`.
+    async fn receive_authentication(&mut self) -> Result<(u32, Vec<u8>), Failure> {
+        match self.receive().await.map_err(down)? {
+            (b'R', body) if body.len() >= 4 => Ok((
+                u32::from_be_bytes([body[0], body[1], body[2], body[3]]),
+                body[4..].to_vec(),
+            )),
+            (b'E', body) => Err(warn(error_message(&body))),
+            _ => Err(down("unexpected message")),
+        }
+    }
+
+    /// Answers authentication requests using cleartext, MD5 or SCRAM-SHA-256 until authenticated.
+    async fn authenticate(&mut self, user: &str, password: Option<&str>) -> Result<(), Failure> {
+        loop {
+            let (code, data) = self.receive_authentication().await?;
+            let password = match (code, password) {
+                (0, _) => return Ok(()),
+                (3 | 5 | 10, Some(password)) => password,
+                (3 | 5 | 10, None) => return Err(warn("password required")),
+                (code, _) => return Err(warn(format!("unsupported authentication method {code}"))),
+            };
+            match code {
+                3 => self.send(b'p', &nul_terminated(password)).await,
+                5 => {
+                    let inner = hex(&md5(format!("{password}{user}").as_bytes()));
+                    let mut outer = inner.into_bytes();
+                    outer.extend(data.iter().take(4));
+                    let hash = format!("md5{}", hex(&md5(&outer)));
+                    self.send(b'p', &nul_terminated(&hash)).await
+                }
+                _ => return self.authenticate_scram(password, &data).await,
+            }
+            .map_err(down)?;
+        }
+    }
+
+    async fn authenticate_scram(
+        &mut self,
+        password: &str,
+        mechanisms: &[u8],
+    ) -> Result<(), Failure> {
+        if !mechanisms
+            .split(|b| *b == 0)
+            .any(|mechanism| mechanism == b"SCRAM-SHA-256")
+        {
+            return Err(warn("unsupported SASL mechanism"));
+        }
+        let mut nonce = [0; 18];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| down("cannot generate nonce"))?;
+        // Username is taken from startup message
+        let client_first_bare = format!("n=,r={}", STANDARD.encode(nonce));
+        let client_first = format!("n,,{client_first_bare}");
+        let mut body = nul_terminated("SCRAM-SHA-256");
+        body.extend((client_first.len() as u32).to_be_bytes());
+        body.extend(client_first.as_bytes());
+        self.send(b'p', &body).await.map_err(down)?;
+
+        let (11, server_first) = self.receive_authentication().await? else {
+            return Err(down("unexpected SASL message"));
+        };
+        let (client_final, server_signature) = scram_client_final(
+            password,
+            &client_first_bare,
+            &String::from_utf8_lossy(&server_first),
+        )
+        .ok_or_else(|| down("invalid SASL message"))?;
+        self.send(b'p', client_final.as_bytes())
+            .await
+            .map_err(down)?;
+
+        let (12, server_final) = self.receive_authentication().await? else {
+            return Err(down("unexpected SASL message"));
+        };
+        // Server proves knowledge of the password, so it cannot be impersonated
+        if server_final.strip_prefix(b"v=") != Some(STANDARD.encode(server_signature).as_bytes()) {
+            return Err(down("invalid SCRAM server signature"));
+        }
+        self.receive_authentication().await.and_then(|(code, _)| {
+            (code == 0)
+                .then_some(())
+                .ok_or_else(|| down("unexpected SASL message"))
+        })
+    }
+
+    /// Skips messages until server is ready for query. Error responses result in `Warn`.
+    async fn wait_until_ready(&mut self) -> Result<(), Failure> {
+        loop {
+            match self.receive().await.map_err(down)? {
+                (b'Z', _) => return Ok(()),
+                (b'E', body) => return Err(warn(error_message(&body))),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Returns the human-readable message field of an error response.
+fn error_message(body: &[u8]) -> String {
+    body.split(|b| *b == 0)
+        .find_map(|field| field.strip_prefix(b"M"))
+        .map(|message| String::from_utf8_lossy(message).to_string())
+        .unwrap_or_else(|| "unknown error".to_string())
+}
+
+/// Computes the client's final SCRAM-SHA-256 message, including proof, as described in RFC 5802.
+/// Also returns the signature expected in the server's final message.
+fn scram_client_final(
+    password: &str,
+    client_first_bare: &str,
+    server_first: &str,
+) -> Option<(String, Vec<u8>)> {
+    let attribute = |message: &str, name: &str| -> Option<String> {
+        message
+            .split(',')
+            .find_map(|attribute| attribute.strip_prefix(name))
+            .map(str::to_string)
+    };
+    let nonce = attribute(server_first, "r=")?;
+    let salt = STANDARD.decode(attribute(server_first, "s=")?).ok()?;
+    let iterations = NonZeroU32::new(attribute(server_first, "i=")?.parse().ok()?)?;
+    if !nonce.starts_with(&attribute(client_first_bare, "r=")?) {
+        return None;
+    }
+
+    let mut salted_password = [0; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        &salt,
+        password.as_bytes(),
+        &mut salted_password,
+    );
+    let client_key = hmac::sign(
+        &hmac::Key::new(hmac::HMAC_SHA256, &salted_password),
+        b"Client Key",
+    );
+    let stored_key = digest::digest(&digest::SHA256, client_key.as_ref());
+    let client_final_without_proof = format!("c=biws,r={nonce}");
+    let auth_message = format!("{client_first_bare},{server_first},{client_final_without_proof}");
+    let client_signature = hmac::sign(
+        &hmac::Key::new(hmac::HMAC_SHA256, stored_key.as_ref()),
+        auth_message.as_bytes(),
+    );
+    let proof = client_key
+        .as_ref()
+        .iter()
+        .zip(client_signature.as_ref())
+        .map(|(key, signature)| key ^ signature)
+        .collect::<Vec<_>>();
+    let server_key = hmac::sign(
+        &hmac::Key::new(hmac::HMAC_SHA256, &salted_password),
+        b"Server Key",
+    );
+    let server_signature = hmac::sign(
+        &hmac::Key::new(hmac::HMAC_SHA256, server_key.as_ref()),
+        auth_message.as_bytes(),
+    );
+    Some((
+        format!("{client_final_without_proof},p={}", STANDARD.encode(proof)),
+        server_signature.as_ref().to_vec(),
+    ))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn md5(data: &[u8]) -> [u8; 16] {
+    Md5::digest(data).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    use crate::checker::postgres::{hex, md5, scram_client_final, Checker};
+    use crate::checker::{accept_once, test_check_config, CheckState};
+    use crate::config::CheckType;
+
+    fn message(tag: u8, body: &[u8]) -> Vec<u8> {
+        let mut message = vec![tag];
+        message.extend(((body.len() + 4) as u32).to_be_bytes());
+        message.extend(body);
+        message
+    }
+
+    /// Accepts a single connection, answers startup and query messages using given replies
+    /// and returns address and handle to receive everything sent by client.
+    async fn serve_postgres(
+        startup_reply: Vec<u8>,
+        query_reply: Vec<u8>,
+    ) -> (String, JoinHandle<Vec<u8>>) {
+        accept_once(move |mut stream| async move {
+            let mut received = Vec::new();
+            let mut buffer = [0; 1024];
+            for reply in [startup_reply, query_reply] {
+                let len = stream.read(&mut buffer).await.unwrap();
+                received.extend(&buffer[..len]);
+                stream.write_all(&reply).await.unwrap();
+            }
+            let _ = stream.read_to_end(&mut received).await;
+            received
+        })
+        .await
+    }
+
+    #[test]
+    fn test_should_compute_md5() {
+        assert_eq!(hex(&md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(
+            hex(&md5(b"The quick brown fox jumps over the lazy dog")),
+            "9e107d9d372bb6826bd81d3542a419d6"
+        );
+    }
+
+    #[test]
+    fn test_should_compute_scram_client_proof() {
+        // Test vector of RFC 7677
+        let client_final = scram_client_final(
+            "pencil",
+            "n=user,r=rOprNGfwEbeRWgbNEkqO",
+            "r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096",
+        );
+
+        let (client_final, server_signature) = client_final.unwrap();
+        assert_eq!(
+            client_final,
+            "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+        );
+        assert_eq!(
+            STANDARD.encode(server_signature),
+            "6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4="
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_return_up_state() {
+        let mut startup_reply = message(b'R', &[0, 0, 0, 0]);
+        startup_reply.extend(message(b'S', b"server_version\x0016.3\0"));
+        startup_reply.extend(message(b'Z', b"I"));
+        let mut query_reply = message(b'C', b"SELECT 1\0");
+        query_reply.extend(message(b'Z', b"I"));
+        let (address, received) = serve_postgres(startup_reply, query_reply).await;
+
+        let check_result = Checker::new(
+            &test_check_config(
+                CheckType::Postgres,
+                &format!("postgres://test@{address}/app"),
+            ),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+        let received = String::from_utf8_lossy(&received.await.unwrap()).to_string();
+        assert!(received.contains("user\0test\0database\0app\0"));
+        assert!(received.contains("Q\0\0\0\x0dSELECT 1\0X"));
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_on_failed_authentication() {
+        let (address, _) = serve_postgres(
+            message(
+                b'E',
+                b"SFATAL\0C28P01\0Mpassword authentication failed for user \"test\"\0\0",
+            ),
+            Vec::new(),
+        )
+        .await;
+
+        let check_result = Checker::new(
+            &test_check_config(
+                CheckType::Postgres,
+                &format!("postgres://test:secret@{address}"),
+            ),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Warn);
+        assert_eq!(
+            check_result.message,
+            Some("password authentication failed for user \"test\"".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_on_failed_query() {
+        let mut startup_reply = message(b'R', &[0, 0, 0, 0]);
+        startup_reply.extend(message(b'Z', b"I"));
+        let mut query_reply = message(
+            b'E',
+            b"SERROR\0C42P01\0Mrelation \"health\" does not exist\0\0",
+        );
+        query_reply.extend(message(b'Z', b"I"));
+        let (address, _) = serve_postgres(startup_reply, query_reply).await;
+
+        let check_result = Checker::new(
+            &test_check_config(CheckType::Postgres, &format!("postgres://test@{address}")),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Warn);
+        assert_eq!(
+            check_result.message,
+            Some("relation \"health\" does not exist".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_return_down_state_on_refused_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+
+        let check_result = Checker::new(
+            &test_check_config(CheckType::Postgres, &format!("postgres://{address}")),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Down);
+    }
+}
@@ -0,0 +1,381 @@
+use std::time::Duration;
+
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Instant};
+
+use crate::checker::{down, url_host, warn, CheckResult, CheckState, Failure};
+use crate::config::CheckConfig;
+
+/// Maximum length of bulk replies, which are only expected for small replies like replication info.
+const MAX_BULK_LEN: usize = 1 << 20;
+
+pub struct Checker<'a> {
+    check_config: &'a CheckConfig,
+    timeout: Duration,
+}
+
+impl Checker<'_> {
+    pub fn new(check_config: &CheckConfig, timeout: Duration) -> Checker<'_> {
+        Checker {
+            check_config,
+            timeout,
+        }
+    }
+
+    pub async fn check(&self) -> CheckResult {
+        let started = Instant::now();
+        let (state, message) = match timeout(self.timeout, self.ping()).await {
+            Ok(result) => result,
+            Err(_) => (CheckState::Timeout, None),
+        };
+
+        let mut check_result = CheckResult::new(self.check_config, state, started);
+        check_result.message = message;
+        check_result
+    }
+
+    /// Authenticates and selects database, if given in URL, and sends `PING`.
+    /// Replicas are also checked for the link to their master.
+    async fn ping(&self) -> (CheckState, Option<String>) {
+        match self.try_ping().await {
+            Ok(()) => (CheckState::Up, None),
+            Err(failure) => failure,
+        }
+    }
+
+    async fn try_ping(&self) -> Result<(), Failure> {
+        let url = Url::parse(&self.check_config.url).map_err(down)?;
+        let host = url_host(&url).ok_or_else(|| down("missing host"))?;
+        let tcp_stream = TcpStream::connect((host, url.port().unwrap_or(6379)))
+            .await
+            .map_err(|err| down(format!("connection failed: {err}")))?;
+        let mut connection = Connection(BufReader::new(tcp_stream));
+
+        if let Some(password) = url.password() {
+            let password = percent_decode_str(password).decode_utf8_lossy();
+            let username = percent_decode_str(url.username()).decode_utf8_lossy();
+            let command = if username.is_empty() {
+                vec!["AUTH", &password]
+            } else {
+                vec!["AUTH", &username, &password]
+            };
+            connection.expect(&command, "+OK").await?;
+        }
+        let db = url.path().trim_start_matches('/');
+        if !db.is_empty() && db != "0" {
+            connection.expect(&["SELECT", db], "+OK").await?;
+        }
+        connection.expect(&["PING"], "+PONG").await?;
+
+        // Ignore errors, e.g. if INFO command is not permitted
+        match connection.command(&["INFO", "replication"]).await {
+            Ok(Some(info)) => check_replication(&info),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Seconds since last interaction of a replica with its master to be considered lagging. Masters ping
+/// their replicas every 10 seconds by default.
+const MAX_MASTER_IO_SECONDS: u64 = 30;
+
+/// Checks replication info of a replica for a broken or stale link to its master.
+fn check_replication(info: &str) -> Result<(), Failure> {
+    let value = |key: &str| {
+        info.lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+    };
+    if value("master_link_status") == Some("down") {
+        return Err(warn("replica not connected to master"));
+    }
+    if value("master_sync_in_progress") == Some("1") {
+        return Err(warn("replica synchronizing with master"));
+    }
+    match value("master_last_io_seconds_ago").and_then(|seconds| seconds.parse::<u64>().ok()) {
+        Some(seconds) if seconds > MAX_MASTER_IO_SECONDS => Err(warn(format!(
+            "replica lagging, last interaction with master {seconds} seconds ago"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+struct Connection(BufReader<TcpStream>);
+
+impl Connection {
+    /// Sends command and returns the reply, which is `None` for null replies. Error replies like `-LOADING`
+    /// or `-MASTERDOWN` are returned as `Warn`, closed connections as `Down`.
+    async fn command(&mut self, args: &[&str]) -> Result<Option<String>, Failure> {
+        let mut command = format!("*{}\r\n", args.len());
+        for arg in args {
+            command.push_str(&format!("${}\r\n{arg}\r\n", arg.len()));
+        }
+        let connection_failed = |err: std::io::Error| down(format!("connection failed: {err}"));
+        self.0
+            .get_mut()
+            .write_all(command.as_bytes())
+            .await
+            .map_err(connection_failed)?;
+
+        let mut line = String::new();
+        if self
+            .0
+            .read_line(&mut line)
+            .await
+            .map_err(connection_failed)?
+            == 0
+        {
+            return Err(down("connection closed"));
+        }
+        let line = line.trim_end();
+        match line.split_at_checked(1) {
+            Some(("-", error)) => Err(warn(error)),
+            Some(("$" | "*", "-1")) | Some(("_", "")) => Ok(None),
+            Some(("$", len)) => {
+                let len = len
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|len| *len <= MAX_BULK_LEN)
+                    .ok_or_else(|| down(format!("invalid reply: {line}")))?;
+                // Bulk string is followed by CRLF
+                let mut bulk = vec![0; len.checked_add(2).ok_or_else(|| down("invalid reply"))?];
+                self.0
+                    .read_exact(&mut bulk)
+                    .await
+                    .map_err(connection_failed)?;
+                bulk.truncate(len);
+                Ok(Some(String::from_utf8_lossy(&bulk).replace("\r\n", "\n")))
+            }
+            _ => Ok(Some(line.to_string())),
+        }
+    }
+
+    /// Sends command and fails with `Warn` if the reply is not as expected.
+    async fn expect(&mut self, args: &[&str], expected: &str) -> Result<(), Failure> {
+        match self.command(args).await? {
+            Some(reply) if reply == expected => Ok(()),
+            Some(reply) => Err(warn(format!("unexpected reply: {reply}"))),
+            None => Err(warn("unexpected null reply")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    use crate::checker::redis::Checker;
+    use crate::checker::{accept_once, test_check_config, CheckState};
+    use crate::config::CheckType;
+
+    /// Accepts a single connection and answers commands using given replies by command name,
+    /// returns address and handle to receive all commands as sent by client.
+    async fn serve_redis(
+        replies: &'static [(&'static str, &'static str)],
+    ) -> (String, JoinHandle<String>) {
+        accept_once(move |mut stream| async move {
+            let mut commands = String::new();
+            let mut buffer = [0; 1024];
+            while let Ok(len) = stream.read(&mut buffer).await {
+                if len == 0 {
+                    break;
+                }
+                let command = String::from_utf8_lossy(&buffer[..len]).to_string();
+                commands.push_str(&command);
+                let name = command.lines().nth(2).unwrap_or_default();
+                let reply = replies
+                    .iter()
+                    .find(|(command, _)| *command == name)
+                    .map(|(_, reply)| *reply)
+                    .unwrap_or("-ERR unknown command\r\n");
+                stream.write_all(reply.as_bytes()).await.unwrap();
+            }
+            commands
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_should_return_up_state() {
+        let (address, commands) = serve_redis(&[
+            ("AUTH", "+OK\r\n"),
+            ("SELECT", "+OK\r\n"),
+            ("PING", "+PONG\r\n"),
+            (
+                "INFO",
+                "$36\r\n# Replication\r\nrole:master\r\nslaves:0\r\n",
+            ),
+        ])
+        .await;
+        let check_config =
+            test_check_config(CheckType::Redis, &format!("redis://:s%40cret@{address}/2"));
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+        assert!(commands
+            .await
+            .unwrap()
+            .starts_with("*2\r\n$4\r\nAUTH\r\n$6\r\ns@cret\r\n*2\r\n$6\r\nSELECT\r\n$1\r\n2\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_while_loading() {
+        let (address, _) = serve_redis(&[(
+            "PING",
+            "-LOADING Redis is loading the dataset in memory\r\n",
+        )])
+        .await;
+
+        let check_result = Checker::new(
+            &test_check_config(CheckType::Redis, &format!("redis://{address}")),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Warn);
+        assert_eq!(
+            check_result.message,
+            Some("LOADING Redis is loading the dataset in memory".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_on_disconnected_replica() {
+        let (address, _) = serve_redis(&[
+            ("PING", "+PONG\r\n"),
+            (
+                "INFO",
+                "$50\r\n# Replication\r\nrole:slave\r\nmaster_link_status:down\r\n",
+            ),
+        ])
+        .await;
+
+        let check_result = Checker::new(
+            &test_check_config(CheckType::Redis, &format!("redis://{address}")),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Warn);
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_on_lagging_replica() {
+        let (address, _) = serve_redis(&[
+            ("PING", "+PONG\r\n"),
+            (
+                "INFO",
+                "$79\r\n# Replication\r\nrole:slave\r\nmaster_link_status:up\r\nmaster_last_io_seconds_ago:45\r\n",
+            ),
+        ])
+        .await;
+
+        let check_result = Checker::new(
+            &test_check_config(CheckType::Redis, &format!("redis://{address}")),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Warn);
+        assert_eq!(
+            check_result.message,
+            Some("replica lagging, last interaction with master 45 seconds ago".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_on_null_reply() {
+        let (address, _) = serve_redis(&[("PING", "$-1\r\n")]).await;
+
+        let check_result = Checker::new(
+            &test_check_config(CheckType::Redis, &format!("redis://{address}")),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Warn);
+        assert_eq!(
+            check_result.message,
+            Some("unexpected null reply".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_return_down_state_on_oversized_bulk_reply() {
+        let (address, _) = serve_redis(&[("PING", "$99999999999\r\n")]).await;
+
+        let check_result = Checker::new(
+            &test_check_config(CheckType::Redis, &format!("redis://{address}")),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Down);
+        assert_eq!(
+            check_result.message,
+            Some("invalid reply: $99999999999".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_return_down_state_on_refused_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+
+        let check_result = Checker::new(
+            &test_check_config(CheckType::Redis, &format!("redis://{address}")),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Down);
+        assert!(check_result
+            .message
+            .unwrap()
+            .starts_with("connection failed: "));
+    }
+
+    #[tokio::test]
+    async fn test_should_connect_to_ipv6_address() {
+        let Ok(listener) = TcpListener::bind("[::1]:0").await else {
+            // IPv6 is not available
+            return;
+        };
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = [0; 1024];
+            while let Ok(len) = stream.read(&mut buffer).await {
+                if len == 0 {
+                    break;
+                }
+                let _ = stream.write_all(b"+PONG\r\n").await;
+            }
+        });
+
+        let check_result = Checker::new(
+            &test_check_config(CheckType::Redis, &format!("redis://{address}")),
+            Duration::from_secs(1),
+        )
+        .check()
+        .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+    }
+}
@@ -1,58 +1,226 @@
 use std::str::FromStr;
+use std::time::Duration;
 
 use reqwest::Url;
-use tokio::io::Interest;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, Interest};
 use tokio::net::TcpStream;
+use tokio::time::{timeout_at, Instant};
 
 use crate::checker::{CheckResult, CheckState};
 use crate::config::CheckConfig;
 
+/// Maximum length of answer to be read when checking expectations.
+const MAX_ANSWER_LEN: usize = 4096;
+
 pub struct Checker<'a> {
     check_config: &'a CheckConfig,
+    timeout: Duration,
 }
 
 impl Checker<'_> {
-    pub fn new(check_config: &CheckConfig) -> Checker {
-        Checker { check_config }
+    pub fn new(check_config: &CheckConfig, timeout: Duration) -> Checker<'_> {
+        Checker {
+            check_config,
+            timeout,
+        }
     }
 
     pub async fn check(&self) -> CheckResult {
         if let Ok(url) = Url::from_str(self.check_config.url.as_str()) {
             if url.scheme() == "tcp" && url.host_str().is_some() && url.port().is_some() {
-                let state = match TcpStream::connect(format!(
+                let started = Instant::now();
+                let deadline = started + self.timeout;
+                let connection = TcpStream::connect(format!(
                     "{}:{}",
                     url.host_str().unwrap(),
                     url.port().unwrap()
-                ))
-                .await
-                {
-                    Ok(tcp_stream) => {
-                        match tcp_stream
-                            .ready(Interest::READABLE | Interest::WRITABLE)
-                            .await
-                        {
-                            Ok(ready) => {
-                                if !ready.is_empty() {
-                                    CheckState::Up
-                                } else {
-                                    CheckState::Warn
-                                }
-                            }
-                            _ => CheckState::Warn,
-                        }
-                    }
-                    _ => CheckState::Down,
-                };
-                return CheckResult {
-                    name: self.check_config.name.to_string(),
-                    state,
+                ));
+                let (state, message) = match timeout_at(deadline, connection).await {
+                    Ok(Ok(tcp_stream)) => self.check_stream(tcp_stream, deadline).await,
+                    Ok(Err(_)) => (CheckState::Down, None),
+                    Err(_) => (CheckState::Timeout, None),
                 };
+                let mut check_result = CheckResult::new(self.check_config, state, started);
+                check_result.message = message;
+                return check_result;
             }
         }
 
-        CheckResult {
-            name: self.check_config.name.to_string(),
-            state: CheckState::Down,
+        CheckResult::new(self.check_config, CheckState::Down, Instant::now())
+    }
+
+    /// Sends configured payload, if any, and checks the answer if expectations are configured.
+    /// Without payload and expectations, the connection only has to become ready.
+    async fn check_stream(
+        &self,
+        mut tcp_stream: TcpStream,
+        deadline: Instant,
+    ) -> (CheckState, Option<String>) {
+        let payload = self.check_config.payload();
+        if payload.is_none() && !self.check_config.has_reply_assertions() {
+            let ready = tcp_stream.ready(Interest::READABLE | Interest::WRITABLE);
+            return match timeout_at(deadline, ready).await {
+                Ok(Ok(ready)) if !ready.is_empty() => (CheckState::Up, None),
+                Ok(_) => (CheckState::Warn, None),
+                Err(_) => (CheckState::Timeout, None),
+            };
+        }
+
+        if let Some(payload) = payload {
+            match timeout_at(deadline, tcp_stream.write_all(&payload)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => return (CheckState::Warn, Some(format!("cannot send: {err}"))),
+                Err(_) => return (CheckState::Timeout, None),
+            }
         }
+        if !self.check_config.has_reply_assertions() {
+            return (CheckState::Up, None);
+        }
+
+        // Read until answer is as expected, connection has been closed or deadline has been reached
+        let mut answer = vec![];
+        let mut buffer = [0; 1024];
+        loop {
+            match timeout_at(deadline, tcp_stream.read(&mut buffer)).await {
+                Ok(Ok(len)) if len > 0 => {
+                    answer.extend_from_slice(&buffer[..len]);
+                    if self
+                        .check_config
+                        .is_expected_reply(&String::from_utf8_lossy(&answer))
+                    {
+                        return (CheckState::Up, None);
+                    }
+                    if answer.len() < MAX_ANSWER_LEN {
+                        continue;
+                    }
+                }
+                Ok(_) => {}
+                Err(_) if answer.is_empty() => return (CheckState::Timeout, None),
+                Err(_) => {}
+            }
+            let answer = String::from_utf8_lossy(&answer);
+            let first_line = answer.lines().next().unwrap_or_default();
+            return (
+                CheckState::Warn,
+                Some(format!("unexpected answer: {first_line}")),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use regex::Regex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use crate::checker::tcp::Checker;
+    use crate::checker::{accept_once, test_check_config, CheckState};
+    use crate::config::CheckType;
+
+    #[tokio::test]
+    async fn test_should_return_up_state() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let check_config = test_check_config(
+            CheckType::Tcp,
+            &format!("tcp://{}", listener.local_addr().unwrap()),
+        );
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+        assert!(check_result.latency.is_some())
+    }
+
+    #[tokio::test]
+    async fn test_should_return_down_state_on_refused_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let check_config = test_check_config(
+            CheckType::Tcp,
+            &format!("tcp://{}", listener.local_addr().unwrap()),
+        );
+        drop(listener);
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Down)
+    }
+
+    /// Accepts a single connection, sends given greeting and answers each received line using given answer.
+    async fn serve_tcp(greeting: &'static str, answer: &'static str) -> String {
+        let (address, _) = accept_once(move |mut stream| async move {
+            stream.write_all(greeting.as_bytes()).await.unwrap();
+            let mut buffer = [0; 1024];
+            while let Ok(len) = stream.read(&mut buffer).await {
+                if len == 0 || stream.write_all(answer.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        })
+        .await;
+        address
+    }
+
+    #[tokio::test]
+    async fn test_should_return_up_state_on_expected_banner() {
+        let address = serve_tcp("SSH-2.0-OpenSSH_9.6\r\n", "").await;
+        let mut check_config = test_check_config(CheckType::Tcp, &format!("tcp://{address}"));
+        check_config.expect = Some("SSH-2.0".to_string());
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_on_unexpected_banner() {
+        let address = serve_tcp("HTTP/1.1 400 Bad Request\r\n", "").await;
+        let mut check_config = test_check_config(CheckType::Tcp, &format!("tcp://{address}"));
+        check_config.expect_regex = Some(Regex::new("^220 ").unwrap());
+
+        let check_result = Checker::new(&check_config, Duration::from_millis(200))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Warn);
+        assert_eq!(
+            check_result.message,
+            Some("unexpected answer: HTTP/1.1 400 Bad Request".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_send_payload_and_check_answer() {
+        let address = serve_tcp("", "+PONG\r\n").await;
+        let mut check_config = test_check_config(CheckType::Tcp, &format!("tcp://{address}"));
+        check_config.send = Some("PING\r\n".to_string());
+        check_config.expect = Some("PONG".to_string());
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+    }
+
+    #[tokio::test]
+    async fn test_should_return_timeout_state_on_missing_answer() {
+        let address = serve_tcp("", "").await;
+        let mut check_config = test_check_config(CheckType::Tcp, &format!("tcp://{address}"));
+        check_config.expect = Some("SSH-2.0".to_string());
+
+        let check_result = Checker::new(&check_config, Duration::from_millis(100))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Timeout);
     }
 }
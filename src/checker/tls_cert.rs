@@ -0,0 +1,299 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use tokio::fs;
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Instant};
+use tokio_rustls::TlsConnector;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::checker::{tls_failure_reason, url_host, CheckResult, CheckState};
+use crate::config::CheckConfig;
+
+pub struct Checker<'a> {
+    check_config: &'a CheckConfig,
+    timeout: Duration,
+}
+
+impl Checker<'_> {
+    pub fn new(check_config: &CheckConfig, timeout: Duration) -> Checker<'_> {
+        Checker {
+            check_config,
+            timeout,
+        }
+    }
+
+    pub async fn check(&self) -> CheckResult {
+        let started = Instant::now();
+        let (state, message) = match timeout(self.timeout, self.get_expiry()).await {
+            Ok(Ok(not_after)) if not_after <= Utc::now() => {
+                let days = (Utc::now() - not_after).num_days();
+                (
+                    CheckState::Down,
+                    Some(format!("certificate expired {days} days ago")),
+                )
+            }
+            Ok(Ok(not_after)) => {
+                let days = (not_after - Utc::now()).num_days();
+                let message = Some(format!("certificate expires in {days} days"));
+                if days < i64::from(self.check_config.warn_days) {
+                    (CheckState::Warn, message)
+                } else {
+                    (CheckState::Up, message)
+                }
+            }
+            Ok(Err(message)) => (CheckState::Down, Some(message)),
+            Err(_) => (CheckState::Timeout, None),
+        };
+
+        let mut check_result = CheckResult::new(self.check_config, state, started);
+        check_result.message = message;
+        check_result
+    }
+
+    /// Connects to configured host and returns expiry of the verified leaf certificate.
+    async fn get_expiry(&self) -> Result<DateTime<Utc>, String> {
+        let url = Url::parse(&self.check_config.url).map_err(|err| err.to_string())?;
+        let host = url_host(&url).ok_or("missing host")?.to_string();
+        let port = url.port_or_known_default().ok_or("missing port")?;
+        let server_name = ServerName::try_from(host.clone()).map_err(|err| err.to_string())?;
+
+        let tcp_stream = TcpStream::connect((host.as_str(), port))
+            .await
+            .map_err(|err| format!("connection failed: {err}"))?;
+        let tls_stream = TlsConnector::from(Arc::new(client_config(self.check_config).await?))
+            .connect(server_name, tcp_stream)
+            .await
+            .map_err(|err| {
+                tls_failure_reason(&err).unwrap_or_else(|| format!("connection failed: {err}"))
+            })?;
+
+        let (_, connection) = tls_stream.get_ref();
+        connection
+            .peer_certificates()
+            .and_then(|certificates| certificates.first())
+            .and_then(|certificate| not_after(certificate))
+            .ok_or_else(|| "certificate error: cannot read expiry".to_string())
+    }
+}
+
+/// Returns TLS configuration trusting built-in root certificates and those in `ca_file`, if any, or any
+/// certificate if `tls_insecure` is set. Client certificate and key are used for mutual TLS, if configured.
+pub(super) async fn client_config(check_config: &CheckConfig) -> Result<ClientConfig, String> {
+    let mut roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    if let Some(ca_file) = &check_config.ca_file {
+        for certificate in rustls_pemfile::certs(&mut read_file(ca_file).await?.as_slice()) {
+            certificate
+                .ok()
+                .and_then(|certificate| roots.add(certificate).ok())
+                .ok_or_else(|| "invalid CA file".to_string())?;
+        }
+    }
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|err| err.to_string())?;
+    let builder = if check_config.tls_insecure {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(provider)))
+    } else {
+        builder.with_root_certificates(roots)
+    };
+    match (&check_config.client_cert, &check_config.client_key) {
+        (Some(cert), Some(key)) => {
+            let invalid = |_| "invalid client certificate".to_string();
+            let certificates = rustls_pemfile::certs(&mut read_file(cert).await?.as_slice())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(invalid)?;
+            let key = rustls_pemfile::private_key(&mut read_file(key).await?.as_slice())
+                .map_err(invalid)?
+                .ok_or_else(|| "invalid client certificate: no private key found".to_string())?;
+            builder
+                .with_client_auth_cert(certificates, key)
+                .map_err(|err| format!("invalid client certificate: {err}"))
+        }
+        _ => Ok(builder.with_no_client_auth()),
+    }
+}
+
+async fn read_file(filename: &str) -> Result<Vec<u8>, String> {
+    fs::read(filename)
+        .await
+        .map_err(|err| format!("cannot read '{filename}': {err}"))
+}
+
+/// Verifier accepting any server certificate, used if `tls_insecure` is set. Handshake signatures are still
+/// verified, so the server must own the key of its certificate.
+#[derive(Debug)]
+struct AcceptAnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Returns end of validity period of given DER encoded X.509 certificate.
+fn not_after(certificate: &[u8]) -> Option<DateTime<Utc>> {
+    let (_, certificate) = X509Certificate::from_der(certificate).ok()?;
+    DateTime::from_timestamp(certificate.validity().not_after.timestamp(), 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::checker::tls_cert::{not_after, Checker};
+    use crate::checker::{serve_tls, test_check_config, CheckState};
+    use crate::config::{CheckConfig, CheckType};
+
+    fn check_config(url: String, warn_days: u32) -> CheckConfig {
+        let mut check_config = test_check_config(CheckType::TlsCert, &url);
+        check_config.ca_file = Some("tests/tls/ca.pem".to_string());
+        check_config.warn_days = warn_days;
+        check_config
+    }
+
+    #[test]
+    fn test_should_read_expiry_of_certificate() {
+        let pem = fs::read("tests/tls/expired.pem").unwrap();
+        let certificate = rustls_pemfile::certs(&mut pem.as_slice())
+            .next()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            not_after(&certificate),
+            Some(Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(not_after(&certificate[..100]), None);
+    }
+
+    #[tokio::test]
+    async fn test_should_return_up_state() {
+        let url = serve_tls("server", false).await.replace("https", "tls");
+
+        let check_result = Checker::new(&check_config(url, 14), Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+        assert!(check_result
+            .message
+            .unwrap()
+            .starts_with("certificate expires in "));
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_on_expiring_certificate() {
+        let url = serve_tls("server", false).await;
+
+        let check_result = Checker::new(&check_config(url, 100_000), Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Warn);
+    }
+
+    #[tokio::test]
+    async fn test_should_return_down_state_on_expired_certificate() {
+        let url = serve_tls("expired", false).await;
+        let mut check_config = check_config(url, 14);
+        check_config.ca_file = Some("tests/tls/expired.pem".to_string());
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Down);
+        assert_eq!(
+            check_result.message.unwrap(),
+            "certificate error: invalid peer certificate: Expired"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_return_down_state_on_expired_certificate_if_insecure() {
+        let url = serve_tls("expired", false).await;
+        let mut check_config = check_config(url, 14);
+        check_config.tls_insecure = true;
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Down);
+        let message = check_result.message.unwrap();
+        assert!(message.starts_with("certificate expired "));
+        assert!(message.ends_with(" days ago"));
+    }
+
+    #[tokio::test]
+    async fn test_should_return_down_state_on_untrusted_certificate() {
+        let url = serve_tls("server", false).await;
+        let mut check_config = check_config(url, 14);
+        check_config.ca_file = None;
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Down);
+        assert!(check_result
+            .message
+            .unwrap()
+            .starts_with("certificate error: "));
+    }
+}
@@ -0,0 +1,147 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use reqwest::Url;
+use tokio::net::{lookup_host, UdpSocket};
+use tokio::time::{timeout, Instant};
+
+use crate::checker::{url_host, CheckResult, CheckState};
+use crate::config::CheckConfig;
+
+pub struct Checker<'a> {
+    check_config: &'a CheckConfig,
+    timeout: Duration,
+}
+
+impl Checker<'_> {
+    pub fn new(check_config: &CheckConfig, timeout: Duration) -> Checker<'_> {
+        Checker {
+            check_config,
+            timeout,
+        }
+    }
+
+    pub async fn check(&self) -> CheckResult {
+        let started = Instant::now();
+        let (state, message) = match timeout(self.timeout, self.probe()).await {
+            Ok(Ok(reply)) if self.check_config.is_expected_reply(&reply) => (CheckState::Up, None),
+            Ok(Ok(reply)) => (CheckState::Warn, Some(format!("unexpected reply: {reply}"))),
+            Ok(Err(message)) => (CheckState::Down, Some(message)),
+            Err(_) => (CheckState::Timeout, None),
+        };
+
+        let mut check_result = CheckResult::new(self.check_config, state, started);
+        check_result.message = message;
+        check_result
+    }
+
+    /// Sends configured payload to host and port of check URL and returns the first reply.
+    async fn probe(&self) -> Result<String, String> {
+        let url = Url::parse(&self.check_config.url).map_err(|err| err.to_string())?;
+        let (Some(host), Some(port)) = (url_host(&url), url.port()) else {
+            return Err("URL must contain host and port".to_string());
+        };
+        let remote_addr = lookup_host((host, port))
+            .await
+            .map_err(|err| err.to_string())?
+            .next()
+            .ok_or_else(|| format!("cannot resolve '{host}'"))?;
+
+        let local_addr = match remote_addr {
+            SocketAddr::V4(_) => SocketAddr::from(([0, 0, 0, 0], 0)),
+            SocketAddr::V6(_) => SocketAddr::from(([0; 16], 0)),
+        };
+        let socket = UdpSocket::bind(local_addr)
+            .await
+            .map_err(|err| err.to_string())?;
+        socket
+            .connect(remote_addr)
+            .await
+            .map_err(|err| err.to_string())?;
+        socket
+            .send(&self.check_config.payload().unwrap_or_default())
+            .await
+            .map_err(|err| err.to_string())?;
+
+        let mut buffer = [0; 4096];
+        let len = socket
+            .recv(&mut buffer)
+            .await
+            .map_err(|err| err.to_string())?;
+        Ok(String::from_utf8_lossy(&buffer[..len]).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::net::UdpSocket;
+
+    use crate::checker::udp::Checker;
+    use crate::checker::{answer_datagram, test_check_config, CheckState};
+    use crate::config::{CheckConfig, CheckType};
+
+    #[tokio::test]
+    async fn test_should_return_up_state_on_expected_reply() {
+        let (server, request) =
+            answer_datagram(|_| b"\xff\xff\xff\xffI source engine".to_vec()).await;
+        let check_config: CheckConfig = toml::from_str(&format!(
+            r#"
+                name = "test"
+                url = "udp://{server}"
+                check_type = "Udp"
+                send_hex = "ff ff ff ff 54"
+                expect_regex = "source"
+            "#
+        ))
+        .unwrap();
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Up);
+        assert_eq!(request.await.unwrap(), vec![0xff, 0xff, 0xff, 0xff, 0x54]);
+    }
+
+    #[tokio::test]
+    async fn test_should_return_warn_state_on_unexpected_reply() {
+        let (server, _) = answer_datagram(|_| b"error".to_vec()).await;
+        let check_config: CheckConfig = toml::from_str(&format!(
+            r#"
+                name = "test"
+                url = "udp://{server}"
+                check_type = "Udp"
+                send = "ping"
+                expect = "pong"
+            "#
+        ))
+        .unwrap();
+
+        let check_result = Checker::new(&check_config, Duration::from_secs(1))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Warn);
+        assert_eq!(
+            check_result.message,
+            Some("unexpected reply: error".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_should_return_timeout_state_on_missing_reply() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let check_config = test_check_config(
+            CheckType::Udp,
+            &format!("udp://{}", socket.local_addr().unwrap()),
+        );
+
+        let check_result = Checker::new(&check_config, Duration::from_millis(100))
+            .check()
+            .await;
+
+        assert_eq!(check_result.state, CheckState::Timeout);
+    }
+}
@@ -1,20 +1,43 @@
 use parse_duration::parse;
-use std::fmt::Formatter;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use std::{env, fs};
 
+use regex::Regex;
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::{Method, StatusCode, Url};
+use serde::de::DeserializeOwned;
 use serde::de::{Error, Visitor};
 use serde::{Deserialize, Deserializer};
+use tokio::sync::watch;
+
+use crate::checker::validate_dns_url;
+use crate::json_path::JsonPathAssertion;
 
 #[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     #[serde(
         default = "default_duration",
         deserialize_with = "deserialize_duration"
     )]
     pub interval: Duration,
+    #[serde(
+        default = "Config::default_timeout",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub timeout: Duration,
     #[serde(default = "Config::default_time_format")]
     pub time_format: String,
+    #[serde(default = "Config::default_concurrency")]
+    pub concurrency: usize,
     #[serde(default)]
     pub colors: ColorConfig,
     #[serde(default)]
@@ -22,27 +45,333 @@ pub struct Config {
 }
 
 impl Config {
+    fn default_timeout() -> Duration {
+        Duration::from_secs(10)
+    }
+
     fn default_time_format() -> String {
         "%R".to_string()
     }
 
-    fn get_config_file() -> String {
-        env::args().nth(1).unwrap_or_else(|| {
-            format!(
-                "{}/.checkbar.toml",
-                dirs::home_dir().unwrap().to_str().unwrap_or("")
-            )
+    fn default_concurrency() -> usize {
+        8
+    }
+
+    /// Returns given config file or the first existing one of
+    /// `$XDG_CONFIG_HOME/checkbar/config.toml`, `$HOME/.checkbar.toml` and `/etc/checkbar/config.toml`.
+    /// Defaults to `$HOME/.checkbar.toml` if none of them exists.
+    pub fn get_config_file(filename: Option<String>) -> String {
+        filename.unwrap_or_else(|| {
+            let home_config_file = dirs::home_dir().unwrap_or_default().join(".checkbar.toml");
+            let candidates = [
+                dirs::config_dir().map(|dir| dir.join("checkbar").join("config.toml")),
+                Some(home_config_file.clone()),
+                Some(PathBuf::from("/etc/checkbar/config.toml")),
+            ];
+            first_existing_file(candidates.into_iter().flatten())
+                .unwrap_or(home_config_file)
+                .to_string_lossy()
+                .to_string()
         })
     }
 
-    pub fn read() -> Self {
-        Self::read_file(Self::get_config_file().as_str())
+    /// Returns the drop-in directory of given config file. This is `checks.d` within a `checkbar` directory,
+    /// e.g. `~/.config/checkbar/checks.d`, otherwise named after the file, e.g. `~/.checkbar.d`.
+    pub fn get_checks_dir(filename: &str) -> PathBuf {
+        let path = Path::new(filename);
+        let parent = path.parent().unwrap_or(Path::new(""));
+        if parent.file_name().is_some_and(|name| name == "checkbar") {
+            return parent.join("checks.d");
+        }
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        parent.join(format!("{stem}.d"))
+    }
+
+    pub fn interval_for(&self, check_config: &CheckConfig) -> Duration {
+        check_config.interval.unwrap_or(self.interval)
+    }
+
+    pub fn timeout_for(&self, check_config: &CheckConfig) -> Duration {
+        check_config.timeout.unwrap_or(self.timeout)
+    }
+
+    /// Reads configuration file. A missing file results in default configuration.
+    /// Checks of all `*.toml` files in the drop-in directory are appended in order of file names.
+    pub fn read_file(filename: &str) -> Result<Self, ConfigError> {
+        let (config, errors) = Self::read(filename, true)?;
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(config),
+        }
+    }
+
+    /// Reads configuration file like `read_file()`, but a missing file is an error.
+    pub fn read_existing_file(filename: &str) -> Result<Self, ConfigError> {
+        match fs::metadata(filename) {
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                Err(ConfigError::Read(filename.to_string(), err.to_string()))
+            }
+            _ => Self::read_file(filename),
+        }
+    }
+
+    /// Reads configuration file, references to environment variables and secrets are only resolved if `resolve` is set.
+    /// Errors of interpolating values are returned along with the configuration.
+    fn read(filename: &str, resolve: bool) -> Result<(Self, Vec<ConfigError>), ConfigError> {
+        let mut config: Config = match fs::read_to_string(filename) {
+            Ok(config) => parse_toml(filename, config.as_str())?,
+            Err(err) if err.kind() == ErrorKind::NotFound => Config::default(),
+            Err(err) => return Err(ConfigError::Read(filename.to_string(), err.to_string())),
+        };
+
+        for fragment_file in Self::get_fragment_files(&Self::get_checks_dir(filename))? {
+            let fragment_filename = fragment_file.to_string_lossy();
+            let fragment: ChecksFragment = match fs::read_to_string(&fragment_file) {
+                Ok(fragment) => parse_toml(&fragment_filename, fragment.as_str())?,
+                Err(err) => {
+                    return Err(ConfigError::Read(
+                        fragment_filename.to_string(),
+                        err.to_string(),
+                    ))
+                }
+            };
+            config.checks.extend(fragment.checks);
+        }
+
+        let errors = config
+            .checks
+            .iter_mut()
+            .flat_map(|check_config| {
+                check_config
+                    .interpolate(resolve)
+                    .into_iter()
+                    .map(|message| ConfigError::Interpolate(check_config.name.to_string(), message))
+            })
+            .collect();
+
+        Ok((config, errors))
+    }
+
+    fn get_fragment_files(checks_dir: &Path) -> Result<Vec<PathBuf>, ConfigError> {
+        let entries = match fs::read_dir(checks_dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => {
+                return Err(ConfigError::Read(
+                    checks_dir.to_string_lossy().to_string(),
+                    err.to_string(),
+                ))
+            }
+        };
+
+        let mut fragment_files = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "toml"))
+            .collect::<Vec<_>>();
+        fragment_files.sort();
+        Ok(fragment_files)
+    }
+
+    /// Reads and validates configuration file. Other than `read_file()`, a missing file is an error
+    /// and references to environment variables and secrets are not resolved, only their syntax is checked.
+    pub fn validate_file(filename: &str) -> Result<Self, Vec<String>> {
+        if !Path::new(filename).exists() {
+            return Err(vec![format!("Cannot read {filename}: file not found")]);
+        }
+        let (config, errors) = Self::read(filename, false).map_err(|err| vec![err.to_string()])?;
+        let mut problems = errors.iter().map(|err| err.to_string()).collect::<Vec<_>>();
+        problems.extend(config.validate());
+        if problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Returns a list of problems found in configuration.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = vec![];
+
+        for (name, color) in [
+            ("up", &self.colors.up),
+            ("warn", &self.colors.warn),
+            ("down", &self.colors.down),
+            ("timeout", &self.colors.timeout),
+            ("unknown", &self.colors.unknown),
+        ] {
+            if !is_hex_color(color) {
+                problems.push(format!(
+                    "Color '{name}': '{color}' is not a valid hex color like '#00FF00'"
+                ));
+            }
+        }
+
+        for (index, check_config) in self.checks.iter().enumerate() {
+            if self.checks[..index]
+                .iter()
+                .any(|other| other.name == check_config.name)
+            {
+                problems.push(format!(
+                    "Check '{}': name is used by another check",
+                    check_config.name
+                ));
+            }
+            problems.extend(
+                check_config
+                    .validate()
+                    .into_iter()
+                    .map(|problem| format!("Check '{}': {problem}", check_config.name)),
+            );
+        }
+
+        problems
+    }
+}
+
+/// Checks to be merged into configuration from drop-in directory.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChecksFragment {
+    #[serde(default)]
+    checks: Vec<CheckConfig>,
+}
+
+fn parse_toml<T: DeserializeOwned>(filename: &str, content: &str) -> Result<T, ConfigError> {
+    toml::from_str(content).map_err(|err| {
+        let (line, column) = match err.span() {
+            Some(span) => line_and_column(content, span.start),
+            None => (1, 1),
+        };
+        ConfigError::Parse {
+            filename: filename.to_string(),
+            line,
+            column,
+            message: err.message().to_string(),
+        }
+    })
+}
+
+fn first_existing_file(candidates: impl IntoIterator<Item = PathBuf>) -> Option<PathBuf> {
+    candidates.into_iter().find(|candidate| candidate.is_file())
+}
+
+/// Replaces `${ENV_VAR}` by value of environment variable, `{file:/path}` by content of file
+/// and `{cmd:command}` by output of shell command. Trailing newlines of file content and command output are removed.
+/// If `resolve` is not set, references are kept as they are and only their syntax is checked.
+fn interpolate(value: &str, resolve: bool) -> Result<String, String> {
+    let mut result = String::new();
+    let mut rest = value;
+
+    while let Some(start) = ["${", "{file:", "{cmd:"]
+        .iter()
+        .filter_map(|prefix| rest.find(prefix).map(|index| (index, *prefix)))
+        .min()
+    {
+        let (index, prefix) = start;
+        result.push_str(&rest[..index]);
+        rest = &rest[index + prefix.len()..];
+
+        let end = closing_brace(rest).ok_or_else(|| format!("missing '}}' after '{prefix}'"))?;
+        let reference = &rest[..end];
+        rest = &rest[end + 1..];
+
+        if reference.is_empty() {
+            return Err(format!("empty reference '{prefix}}}'"));
+        }
+        if !resolve {
+            result.push_str(&format!("{prefix}{reference}}}"));
+            continue;
+        }
+
+        let resolved = match prefix {
+            "${" => env::var(reference)
+                .map_err(|_| format!("environment variable '{reference}' is not set"))?,
+            "{file:" => fs::read_to_string(reference)
+                .map_err(|err| format!("cannot read secret file '{reference}': {err}"))?,
+            _ => {
+                let output = Command::new("sh")
+                    .arg("-c")
+                    .arg(reference)
+                    .output()
+                    .map_err(|err| format!("cannot run secret command '{reference}': {err}"))?;
+                if !output.status.success() {
+                    return Err(format!(
+                        "secret command '{reference}' failed with {}",
+                        output.status
+                    ));
+                }
+                String::from_utf8_lossy(&output.stdout).to_string()
+            }
+        };
+        result.push_str(resolved.trim_end_matches(['\r', '\n']));
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Returns index of the brace closing a reference, skipping nested pairs of braces,
+/// e.g. within `{cmd:awk '{print $1}' file}`.
+fn closing_brace(value: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in value.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(index),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn is_hex_color(value: &str) -> bool {
+    match value.strip_prefix('#') {
+        Some(hex) => {
+            (hex.len() == 6 || hex.len() == 8) && hex.chars().all(|c| c.is_ascii_hexdigit())
+        }
+        None => false,
     }
+}
 
-    pub fn read_file(filename: &str) -> Self {
-        match fs::read_to_string(filename) {
-            Ok(config) => toml::from_str(config.as_str()).unwrap_or_default(),
-            Err(_) => Config::default(),
+/// Returns one-based line and column of given byte offset.
+fn line_and_column(value: &str, offset: usize) -> (usize, usize) {
+    let before = &value[..offset.min(value.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+    (line, column)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigError {
+    Read(String, String),
+    Parse {
+        filename: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    Interpolate(String, String),
+    UnknownChecks(Vec<String>),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Read(filename, message) => write!(f, "Cannot read {filename}: {message}"),
+            Self::Parse {
+                filename,
+                line,
+                column,
+                message,
+            } => write!(f, "Cannot parse {filename}:{line}:{column}: {message}"),
+            Self::Interpolate(name, message) => {
+                write!(f, "Cannot resolve value of check '{name}': {message}")
+            }
+            Self::UnknownChecks(names) => {
+                write!(f, "No check configured with name '{}'", names.join("', '"))
+            }
         }
     }
 }
@@ -51,18 +380,136 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             interval: Duration::from_secs(60),
+            timeout: Self::default_timeout(),
             time_format: Self::default_time_format(),
+            concurrency: Self::default_concurrency(),
             colors: ColorConfig::default(),
             checks: vec![],
         }
     }
 }
 
+/// Values given on command line, taking precedence over configuration file.
+#[derive(Clone, Default)]
+pub struct ConfigOverrides {
+    pub interval: Option<Duration>,
+    pub only: Vec<String>,
+}
+
+impl ConfigOverrides {
+    /// Replaces global and per-check intervals and removes all checks not selected by name.
+    /// Names not matching any check are an error.
+    pub fn apply(&self, config: &mut Config) -> Result<(), ConfigError> {
+        let unknown_names = self
+            .only
+            .iter()
+            .filter(|name| !config.checks.iter().any(|check| &&check.name == name))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !unknown_names.is_empty() {
+            return Err(ConfigError::UnknownChecks(unknown_names));
+        }
+
+        if let Some(interval) = self.interval {
+            config.interval = interval;
+            for check_config in &mut config.checks {
+                check_config.interval = None;
+            }
+        }
+        if !self.only.is_empty() {
+            config
+                .checks
+                .retain(|check_config| self.only.contains(&check_config.name));
+        }
+        Ok(())
+    }
+}
+
+/// Configuration held in memory and shared between all tasks.
+/// It is replaced as a whole on reload, so readers always get a consistent configuration.
+/// If the configuration file cannot be read, the last valid configuration is kept.
+#[derive(Clone)]
+pub struct SharedConfig {
+    filename: String,
+    overrides: ConfigOverrides,
+    sender: Arc<watch::Sender<Arc<Config>>>,
+    error: Arc<RwLock<Option<ConfigError>>>,
+}
+
+impl SharedConfig {
+    pub fn new(filename: &str, overrides: ConfigOverrides) -> Self {
+        let (sender, _) = watch::channel(Arc::new(Config::default()));
+        let config = Self {
+            filename: filename.to_string(),
+            overrides,
+            sender: Arc::new(sender),
+            error: Arc::new(RwLock::new(None)),
+        };
+        config.update(Config::read_file(filename));
+        config
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn get(&self) -> Arc<Config> {
+        self.sender.borrow().clone()
+    }
+
+    /// Returns the error of the last attempt to read the configuration file, if any.
+    pub fn error(&self) -> Option<ConfigError> {
+        self.error.read().unwrap().clone()
+    }
+
+    /// Reloads the configuration file. Other than on first load, a missing file is an error,
+    /// so the last valid configuration is kept while an editor replaces the file.
+    pub fn reload(&self) {
+        self.update(Config::read_existing_file(&self.filename));
+    }
+
+    fn update(&self, result: Result<Config, ConfigError>) {
+        let result = result.and_then(|mut config| {
+            self.overrides.apply(&mut config)?;
+            Ok(config)
+        });
+        match result {
+            Ok(config) => {
+                *self.error.write().unwrap() = None;
+                self.sender.send_replace(Arc::new(config));
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                *self.error.write().unwrap() = Some(err);
+            }
+        }
+    }
+
+    /// Returns a receiver to be notified about configuration changes.
+    pub fn subscribe(&self) -> watch::Receiver<Arc<Config>> {
+        self.sender.subscribe()
+    }
+}
+
 #[derive(Deserialize)]
 pub struct ColorConfig {
     pub up: String,
     pub warn: String,
     pub down: String,
+    #[serde(default = "ColorConfig::default_timeout")]
+    pub timeout: String,
+    #[serde(default = "ColorConfig::default_unknown")]
+    pub unknown: String,
+}
+
+impl ColorConfig {
+    fn default_timeout() -> String {
+        String::from("#FF8000")
+    }
+
+    fn default_unknown() -> String {
+        String::from("#808080")
+    }
 }
 
 impl Default for ColorConfig {
@@ -71,23 +518,465 @@ impl Default for ColorConfig {
             up: String::from("#00FF00"),
             warn: String::from("#FFFF00"),
             down: String::from("#FF0000"),
+            timeout: Self::default_timeout(),
+            unknown: Self::default_unknown(),
         }
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct CheckConfig {
     pub name: String,
+    #[serde(default)]
     pub url: String,
     pub check_type: Option<CheckType>,
+    pub command: Option<String>,
     pub click_cmd: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub interval: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub timeout: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub warn_latency: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub down_latency: Option<Duration>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    pub basic_auth: Option<BasicAuthConfig>,
+    pub bearer_token: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_method")]
+    pub method: Option<Method>,
+    pub body: Option<String>,
+    pub expected_status: Option<ExpectedStatus>,
+    pub body_contains: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_regex")]
+    pub body_regex: Option<Regex>,
+    pub json_path: Option<JsonPathAssertion>,
+    pub ca_file: Option<String>,
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    #[serde(default)]
+    pub tls_insecure: bool,
+    #[serde(default = "CheckConfig::default_warn_days")]
+    pub warn_days: u32,
+    pub send: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_hex")]
+    pub send_hex: Option<Vec<u8>>,
+    pub expect: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_regex")]
+    pub expect_regex: Option<Regex>,
+    #[serde(default = "CheckConfig::default_query")]
+    pub query: String,
+}
+
+/// Expected HTTP status codes, e.g. `"200-299,401"`, `401` or `[200, "300-399"]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpectedStatus(Vec<RangeInclusive<u16>>);
+
+impl ExpectedStatus {
+    pub fn contains(&self, status: u16) -> bool {
+        self.0.iter().any(|range| range.contains(&status))
+    }
+}
+
+impl FromStr for ExpectedStatus {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parse_status = |status: &str| {
+            status
+                .trim()
+                .parse::<u16>()
+                .ok()
+                .filter(|status| (100..=999).contains(status))
+                .ok_or_else(|| format!("invalid status code '{}'", status.trim()))
+        };
+
+        value
+            .split(',')
+            .map(|part| match part.split_once('-') {
+                Some((start, end)) => {
+                    let range = parse_status(start)?..=parse_status(end)?;
+                    if range.is_empty() {
+                        return Err(format!("invalid status code range '{}'", part.trim()));
+                    }
+                    Ok(range)
+                }
+                None => parse_status(part).map(|status| status..=status),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(ExpectedStatus)
+    }
+}
+
+impl<'de> Deserialize<'de> for ExpectedStatus {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Value {
+            Number(u16),
+            Text(String),
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Values {
+            Single(Value),
+            List(Vec<Value>),
+        }
+
+        let values = match Values::deserialize(d)? {
+            Values::Single(value) => vec![value],
+            Values::List(values) => values,
+        };
+        values
+            .into_iter()
+            .map(|value| match value {
+                Value::Number(status) => status.to_string(),
+                Value::Text(text) => text,
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct BasicAuthConfig {
+    pub username: String,
+    pub password: Option<String>,
+}
+
+impl CheckConfig {
+    fn default_warn_days() -> u32 {
+        14
+    }
+
+    fn default_query() -> String {
+        "SELECT 1".to_string()
+    }
+
+    /// Returns whether the HTTP status is expected, defaults to any successful status.
+    pub fn is_expected_status(&self, status: StatusCode) -> bool {
+        match &self.expected_status {
+            Some(expected_status) => expected_status.contains(status.as_u16()),
+            None => status.is_success(),
+        }
+    }
+
+    /// Returns whether any assertion on response body is configured.
+    pub fn has_body_assertions(&self) -> bool {
+        self.body_contains.is_some() || self.body_regex.is_some() || self.json_path.is_some()
+    }
+
+    /// Returns whether the response body satisfies all configured assertions.
+    /// A JSON path assertion never holds if the body is not a JSON document.
+    pub fn is_expected_body(&self, body: &str) -> bool {
+        if let Some(body_contains) = &self.body_contains {
+            if !body.contains(body_contains.as_str()) {
+                return false;
+            }
+        }
+        if let Some(body_regex) = &self.body_regex {
+            if !body_regex.is_match(body) {
+                return false;
+            }
+        }
+        if let Some(json_path) = &self.json_path {
+            return match serde_json::from_str(body) {
+                Ok(document) => json_path.matches(&document),
+                Err(_) => false,
+            };
+        }
+        true
+    }
+
+    /// Returns payload to be sent, either given as text or hex encoded bytes.
+    pub fn payload(&self) -> Option<Vec<u8>> {
+        match (&self.send, &self.send_hex) {
+            (Some(send), _) => Some(send.as_bytes().to_vec()),
+            (None, Some(send_hex)) => Some(send_hex.clone()),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns whether any expectation on a received reply is configured.
+    pub fn has_reply_assertions(&self) -> bool {
+        self.expect.is_some() || self.expect_regex.is_some()
+    }
+
+    /// Returns whether the reply satisfies all configured expectations.
+    pub fn is_expected_reply(&self, reply: &str) -> bool {
+        self.expect
+            .as_ref()
+            .is_none_or(|expect| reply.contains(expect.as_str()))
+            && self
+                .expect_regex
+                .as_ref()
+                .is_none_or(|expect_regex| expect_regex.is_match(reply))
+    }
+
+    /// Resolves environment variables and secrets in URL, commands, headers, credentials and body.
+    /// Interpolates all values which may contain references, see `interpolate()`.
+    /// Returns the errors of all values which cannot be interpolated.
+    fn interpolate(&mut self, resolve: bool) -> Vec<String> {
+        let mut errors = vec![];
+        let mut interpolate_value = |value: &mut String| match interpolate(value, resolve) {
+            Ok(interpolated) => *value = interpolated,
+            Err(err) => errors.push(err),
+        };
+
+        interpolate_value(&mut self.url);
+        if let Some(command) = &mut self.command {
+            interpolate_value(command);
+        }
+        if let Some(click_cmd) = &mut self.click_cmd {
+            interpolate_value(click_cmd);
+        }
+        for value in self.headers.values_mut() {
+            interpolate_value(value);
+        }
+        if let Some(basic_auth) = &mut self.basic_auth {
+            interpolate_value(&mut basic_auth.username);
+            if let Some(password) = &mut basic_auth.password {
+                interpolate_value(password);
+            }
+        }
+        if let Some(bearer_token) = &mut self.bearer_token {
+            interpolate_value(bearer_token);
+        }
+        if let Some(body) = &mut self.body {
+            interpolate_value(body);
+        }
+        errors
+    }
+
+    /// Returns a list of problems found in check configuration.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = vec![];
+
+        if let (Some(warn_latency), Some(down_latency)) = (self.warn_latency, self.down_latency) {
+            if warn_latency >= down_latency {
+                problems.push("'warn_latency' must be less than 'down_latency'".to_string());
+            }
+        }
+        if self.basic_auth.is_some() && self.bearer_token.is_some() {
+            problems.push("Only one of 'basic_auth' and 'bearer_token' can be used".to_string());
+        }
+        if self.send.is_some() && self.send_hex.is_some() {
+            problems.push("Only one of 'send' and 'send_hex' can be used".to_string());
+        }
+        if self.client_cert.is_some() != self.client_key.is_some() {
+            problems.push("'client_cert' and 'client_key' must be used together".to_string());
+        }
+        for (option, filename) in [
+            ("ca_file", &self.ca_file),
+            ("client_cert", &self.client_cert),
+            ("client_key", &self.client_key),
+        ] {
+            if let Some(filename) = filename {
+                if !Path::new(filename).is_file() {
+                    problems.push(format!("File '{filename}' of '{option}' does not exist"));
+                }
+            }
+        }
+        for (name, value) in &self.headers {
+            if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                problems.push(format!("Header name '{name}' is not valid"));
+            } else if HeaderValue::from_str(value).is_err() {
+                problems.push(format!("Header value of '{name}' is not valid"));
+            }
+        }
+
+        if self.check_type == Some(CheckType::Command) {
+            if self.command.is_none() {
+                problems.push("'command' is required for check type 'Command'".to_string());
+            }
+            return problems;
+        }
+
+        if self.url.is_empty() {
+            problems.push(format!(
+                "'url' is required for check type '{:?}'",
+                self.check_type.clone().unwrap_or(CheckType::Http)
+            ));
+            return problems;
+        }
+        // References are not resolved when validating, so the URL cannot be checked
+        if ["${", "{file:", "{cmd:"]
+            .iter()
+            .any(|prefix| self.url.contains(prefix))
+        {
+            return problems;
+        }
+        let url = match Url::parse(&self.url) {
+            Ok(url) => url,
+            Err(err) => {
+                problems.push(format!("URL '{}' is not valid: {err}", self.url));
+                return problems;
+            }
+        };
+
+        match self.check_type {
+            Some(CheckType::Tcp) => {
+                if url.scheme() != "tcp" {
+                    problems.push(format!(
+                        "URL '{}' must use scheme 'tcp' for check type 'Tcp'",
+                        self.url
+                    ));
+                }
+                if url.host_str().is_none() || url.port().is_none() {
+                    problems.push(format!(
+                        "URL '{}' must contain host and port for check type 'Tcp'",
+                        self.url
+                    ));
+                }
+            }
+            Some(CheckType::Dns) => {
+                if url.scheme() != "dns" {
+                    problems.push(format!(
+                        "URL '{}' must use scheme 'dns' for check type 'Dns'",
+                        self.url
+                    ));
+                }
+                if let Err(problem) = validate_dns_url(&self.url) {
+                    problems.push(format!("URL '{}' is not valid: {problem}", self.url));
+                }
+            }
+            Some(CheckType::Postgres) => {
+                if url.scheme() != "postgres" && url.scheme() != "postgresql" {
+                    problems.push(format!(
+                        "URL '{}' must use scheme 'postgres' or 'postgresql' for check type 'Postgres'",
+                        self.url
+                    ));
+                }
+                if url.host_str().is_none() {
+                    problems.push(format!(
+                        "URL '{}' must contain host for check type 'Postgres'",
+                        self.url
+                    ));
+                }
+            }
+            Some(CheckType::Mysql) => {
+                if url.scheme() != "mysql" {
+                    problems.push(format!(
+                        "URL '{}' must use scheme 'mysql' for check type 'Mysql'",
+                        self.url
+                    ));
+                }
+                if url.host_str().is_none() {
+                    problems.push(format!(
+                        "URL '{}' must contain host for check type 'Mysql'",
+                        self.url
+                    ));
+                }
+            }
+            Some(CheckType::Grpc) => {
+                if url.scheme() != "grpc" && url.scheme() != "grpcs" {
+                    problems.push(format!(
+                        "URL '{}' must use scheme 'grpc' or 'grpcs' for check type 'Grpc'",
+                        self.url
+                    ));
+                }
+                if url.host_str().is_none() || url.port().is_none() {
+                    problems.push(format!(
+                        "URL '{}' must contain host and port for check type 'Grpc'",
+                        self.url
+                    ));
+                }
+            }
+            Some(CheckType::Redis) => {
+                if url.scheme() != "redis" {
+                    problems.push(format!(
+                        "URL '{}' must use scheme 'redis' for check type 'Redis'",
+                        self.url
+                    ));
+                }
+                if url.host_str().is_none() {
+                    problems.push(format!(
+                        "URL '{}' must contain host for check type 'Redis'",
+                        self.url
+                    ));
+                }
+                if !url
+                    .path()
+                    .trim_start_matches('/')
+                    .chars()
+                    .all(|c| c.is_ascii_digit())
+                {
+                    problems.push(format!(
+                        "URL '{}' must contain database number as path for check type 'Redis'",
+                        self.url
+                    ));
+                }
+            }
+            Some(CheckType::Udp) => {
+                if url.scheme() != "udp" {
+                    problems.push(format!(
+                        "URL '{}' must use scheme 'udp' for check type 'Udp'",
+                        self.url
+                    ));
+                }
+                if url.host_str().is_none() || url.port().is_none() {
+                    problems.push(format!(
+                        "URL '{}' must contain host and port for check type 'Udp'",
+                        self.url
+                    ));
+                }
+            }
+            Some(CheckType::TlsCert) => {
+                if url.scheme() != "tls" && url.scheme() != "https" {
+                    problems.push(format!(
+                        "URL '{}' must use scheme 'tls' or 'https' for check type 'TlsCert'",
+                        self.url
+                    ));
+                }
+                if url.host_str().is_none() || url.port_or_known_default().is_none() {
+                    problems.push(format!(
+                        "URL '{}' must contain host and port for check type 'TlsCert'",
+                        self.url
+                    ));
+                }
+            }
+            _ => {
+                if url.scheme() != "http" && url.scheme() != "https" {
+                    problems.push(format!(
+                        "URL '{}' must use scheme 'http' or 'https' for check type '{}'",
+                        self.url,
+                        self.check_type.as_ref().unwrap_or(&CheckType::Http)
+                    ));
+                }
+            }
+        }
+
+        problems
+    }
 }
 
-#[derive(Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
 pub enum CheckType {
     Http,
     Actuator,
     Tcp,
+    TlsCert,
+    Dns,
+    Udp,
+    Command,
+    Redis,
+    Postgres,
+    Mysql,
+    Grpc,
+}
+
+impl Display for CheckType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
 }
 
 fn default_duration() -> Duration {
@@ -123,22 +1012,83 @@ where
     }
 
     match d.deserialize_string(StringVisitor) {
-        Ok(value) => {
-            if let Ok(duration) = parse(value.as_str()) {
-                Ok(duration)
-            } else {
-                Ok(Duration::from_secs(60))
-            }
-        }
+        Ok(value) => parse(value.as_str()).map_err(|_| {
+            D::Error::custom(format!(
+                "invalid duration '{value}', expected e.g. 60 or \"1m 30s\""
+            ))
+        }),
         Err(err) => Err(err),
     }
 }
 
+fn deserialize_regex<'de, D>(d: D) -> Result<Option<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let regex = String::deserialize(d)?;
+    Regex::new(&regex)
+        .map(Some)
+        .map_err(|err| D::Error::custom(format!("invalid regex '{regex}': {err}")))
+}
+
+fn deserialize_hex<'de, D>(d: D) -> Result<Option<Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let hex = String::deserialize(d)?;
+    let digits = hex
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_digit(16).map(|digit| digit as u8))
+        .collect::<Option<Vec<_>>>()
+        .filter(|digits| digits.len() % 2 == 0)
+        .ok_or_else(|| D::Error::custom(format!("invalid hex value '{hex}'")))?;
+    Ok(Some(
+        digits
+            .chunks(2)
+            .map(|digits| digits[0] << 4 | digits[1])
+            .collect(),
+    ))
+}
+
+fn deserialize_method<'de, D>(d: D) -> Result<Option<Method>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let method = String::deserialize(d)?;
+    Method::from_bytes(method.to_uppercase().as_bytes())
+        .map(Some)
+        .map_err(|_| D::Error::custom(format!("invalid HTTP method '{method}'")))
+}
+
+fn deserialize_optional_duration<'de, D>(d: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_duration(d).map(Some)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::path::PathBuf;
     use std::time::Duration;
+    use std::{env, fs};
+
+    use reqwest::{Method, StatusCode};
+
+    use crate::config::{
+        first_existing_file, interpolate, parse_toml, Config, ConfigError, ConfigOverrides,
+        ExpectedStatus, SharedConfig,
+    };
 
-    use crate::config::Config;
+    /// Returns path of a config file within a new temporary directory of given name,
+    /// so tests do not read drop-in directories or other files of the shared temporary directory.
+    fn temp_config_file(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("checkbar-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory.join("config.toml")
+    }
 
     #[test]
     fn test_should_parse_config_with_number_interval() {
@@ -210,24 +1160,828 @@ mod tests {
         assert_eq!(config.colors.up, "#00FF00".to_string());
         assert_eq!(config.colors.warn, "#FFFF00".to_string());
         assert_eq!(config.colors.down, "#FF0000".to_string());
+        assert_eq!(config.colors.timeout, "#FF8000".to_string());
+        assert_eq!(config.colors.unknown, "#808080".to_string());
     }
 
     #[test]
-    fn test_should_read_and_parse_file() {
-        let config = Config::read_file("./tests/testconfig1.toml");
-        assert_eq!(config.interval, Duration::from_secs(10));
-        assert_eq!(config.checks.len(), 1);
-        assert_eq!(config.checks[0].name, "www");
-        assert_eq!(config.checks[0].url, "https://example.com");
+    fn test_should_parse_config_with_concurrency() {
+        let config: Config = toml::from_str(
+            r#"
+                concurrency = 2
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.concurrency, 2);
     }
 
     #[test]
-    fn test_should_return_default_if_no_config_file() {
-        let config = Config::read_file("./tests/no_testconfig.toml");
-        assert_eq!(config.interval, Duration::from_secs(60));
-        assert_eq!(config.colors.up, "#00FF00".to_string());
-        assert_eq!(config.colors.warn, "#FFFF00".to_string());
-        assert_eq!(config.colors.down, "#FF0000".to_string());
-        assert_eq!(config.checks.len(), 0);
+    fn test_should_parse_config_and_use_default_concurrency() {
+        let config: Config = toml::from_str(
+            r#"
+                interval = "2m 3s"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.concurrency, 8);
+    }
+
+    #[test]
+    fn test_should_parse_config_with_check_interval() {
+        let config: Config = toml::from_str(
+            r#"
+                interval = "1m"
+
+                [[checks]]
+                name = "example"
+                url = "https://example.com"
+
+                [[checks]]
+                name = "fast example"
+                url = "https://fast.example.com"
+                interval = "10s"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.checks[0].interval, None);
+        assert_eq!(
+            config.interval_for(&config.checks[0]),
+            Duration::from_secs(60)
+        );
+        assert_eq!(config.checks[1].interval, Some(Duration::from_secs(10)));
+        assert_eq!(
+            config.interval_for(&config.checks[1]),
+            Duration::from_secs(10)
+        );
+    }
+
+    #[test]
+    fn test_should_parse_config_with_timeouts() {
+        let config: Config = toml::from_str(
+            r#"
+                timeout = "5s"
+
+                [[checks]]
+                name = "example"
+                url = "https://example.com"
+
+                [[checks]]
+                name = "slow example"
+                url = "https://slow.example.com"
+                timeout = "30s"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.timeout, Duration::from_secs(5));
+        assert_eq!(config.checks[0].timeout, None);
+        assert_eq!(
+            config.timeout_for(&config.checks[0]),
+            Duration::from_secs(5)
+        );
+        assert_eq!(config.checks[1].timeout, Some(Duration::from_secs(30)));
+        assert_eq!(
+            config.timeout_for(&config.checks[1]),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn test_should_parse_config_and_use_default_timeout() {
+        let config: Config = toml::from_str(
+            r#"
+                [[checks]]
+                name = "example"
+                url = "https://example.com"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.timeout, Duration::from_secs(10));
+        assert_eq!(
+            config.timeout_for(&config.checks[0]),
+            Duration::from_secs(10)
+        );
+    }
+
+    #[test]
+    fn test_should_read_and_parse_file() {
+        let config = Config::read_file("./tests/testconfig1.toml").unwrap();
+        assert_eq!(config.interval, Duration::from_secs(10));
+        assert_eq!(config.checks.len(), 1);
+        assert_eq!(config.checks[0].name, "www");
+        assert_eq!(config.checks[0].url, "https://example.com");
+    }
+
+    #[test]
+    fn test_should_read_and_merge_checks_from_drop_in_directory() {
+        let config = Config::read_file("./tests/checkbar/config.toml").unwrap();
+        assert_eq!(config.interval, Duration::from_secs(30));
+        assert_eq!(
+            config
+                .checks
+                .iter()
+                .map(|check_config| check_config.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["main", "team", "project"]
+        );
+    }
+
+    #[test]
+    fn test_should_return_drop_in_directory_for_config_file() {
+        assert_eq!(
+            Config::get_checks_dir("/home/user/.config/checkbar/config.toml"),
+            PathBuf::from("/home/user/.config/checkbar/checks.d")
+        );
+        assert_eq!(
+            Config::get_checks_dir("/home/user/.checkbar.toml"),
+            PathBuf::from("/home/user/.checkbar.d")
+        );
+        assert_eq!(
+            Config::get_checks_dir("/etc/work.toml"),
+            PathBuf::from("/etc/work.d")
+        );
+    }
+
+    #[test]
+    fn test_should_not_accept_other_settings_in_drop_in_files() {
+        let fragment = parse_toml::<super::ChecksFragment>("fragment.toml", "interval = 10");
+        assert!(matches!(fragment, Err(ConfigError::Parse { .. })));
+    }
+
+    #[test]
+    fn test_should_find_first_existing_config_file() {
+        assert_eq!(
+            first_existing_file([
+                PathBuf::from("./tests/no_testconfig.toml"),
+                PathBuf::from("./tests/testconfig1.toml"),
+                PathBuf::from("./tests/checkbar/config.toml"),
+            ]),
+            Some(PathBuf::from("./tests/testconfig1.toml"))
+        );
+        assert_eq!(
+            first_existing_file([PathBuf::from("./tests/no_testconfig.toml")]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_should_return_default_if_no_config_file() {
+        let config = Config::read_file("./tests/no_testconfig.toml").unwrap();
+        assert_eq!(config.interval, Duration::from_secs(60));
+        assert_eq!(config.colors.up, "#00FF00".to_string());
+        assert_eq!(config.colors.warn, "#FFFF00".to_string());
+        assert_eq!(config.colors.down, "#FF0000".to_string());
+        assert_eq!(config.checks.len(), 0);
+    }
+
+    #[test]
+    fn test_should_return_error_if_existing_config_file_required() {
+        let config = Config::read_existing_file("./tests/no_testconfig.toml");
+        match config {
+            Err(ConfigError::Read(filename, _)) => {
+                assert_eq!(filename, "./tests/no_testconfig.toml")
+            }
+            _ => panic!("Expected read error"),
+        }
+        assert!(Config::read_existing_file("./tests/testconfig1.toml").is_ok());
+    }
+
+    #[test]
+    fn test_should_return_error_with_position_if_config_not_parseable() {
+        let config = parse_toml::<Config>(
+            "test.toml",
+            r#"interval = 10
+
+[[checks]]
+name = "example"
+url = https://example.com
+"#,
+        );
+
+        match config {
+            Err(ConfigError::Parse {
+                filename,
+                line,
+                column,
+                ..
+            }) => {
+                assert_eq!(filename, "test.toml");
+                assert_eq!(line, 5);
+                assert_eq!(column, 7);
+            }
+            _ => panic!("Expected parse error"),
+        }
+    }
+
+    #[test]
+    fn test_should_return_error_for_unparseable_interval() {
+        let config = parse_toml::<Config>("test.toml", r#"interval = "1x 30y""#);
+
+        match config {
+            Err(ConfigError::Parse { line, message, .. }) => {
+                assert_eq!(line, 1);
+                assert!(message.contains("invalid duration '1x 30y'"));
+            }
+            _ => panic!("Expected parse error"),
+        }
+    }
+
+    #[test]
+    fn test_should_return_error_for_misspelled_key() {
+        let config = parse_toml::<Config>(
+            "test.toml",
+            r#"
+[[checks]]
+name = "example"
+url = "https://example.com"
+expected_stauts = 404
+"#,
+        );
+
+        match config {
+            Err(ConfigError::Parse { line, message, .. }) => {
+                assert_eq!(line, 5);
+                assert!(message.contains("unknown field `expected_stauts`"));
+            }
+            _ => panic!("Expected parse error"),
+        }
+        assert!(parse_toml::<Config>("test.toml", "intervall = 10").is_err());
+    }
+
+    #[test]
+    fn test_should_keep_last_valid_shared_config_on_error() {
+        let path = temp_config_file("keep-last-valid");
+        let filename = path.to_str().unwrap();
+        fs::write(filename, "interval = 10").unwrap();
+
+        let config = SharedConfig::new(filename, ConfigOverrides::default());
+        assert_eq!(config.get().interval, Duration::from_secs(10));
+        assert!(config.error().is_none());
+
+        fs::write(filename, "interval = ").unwrap();
+        config.reload();
+        assert_eq!(config.get().interval, Duration::from_secs(10));
+        assert!(config.error().is_some());
+
+        fs::write(filename, "interval = 20").unwrap();
+        config.reload();
+        assert_eq!(config.get().interval, Duration::from_secs(20));
+        assert!(config.error().is_none());
+
+        fs::remove_file(filename).unwrap();
+        config.reload();
+        assert_eq!(config.get().interval, Duration::from_secs(20));
+        assert!(config.error().is_some());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_should_validate_config_without_problems() {
+        let config: Config = toml::from_str(
+            r##"
+                [colors]
+                up = "#00ff00"
+                warn = "#FFFF00"
+                down = "#FF000080"
+
+                [[checks]]
+                name = "http"
+                url = "https://example.com"
+
+                [[checks]]
+                name = "actuator"
+                url = "http://example.com/actuator/health"
+                check_type = "Actuator"
+
+                [[checks]]
+                name = "tcp"
+                url = "tcp://example.com:22"
+                check_type = "Tcp"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(config.validate(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_should_validate_config_with_problems() {
+        let config: Config = toml::from_str(
+            r##"
+                [colors]
+                up = "green"
+                warn = "#FFFF00"
+                down = "#FF00"
+
+                [[checks]]
+                name = "http"
+                url = "tcp://example.com:80"
+
+                [[checks]]
+                name = "http"
+                url = "example.com"
+
+                [[checks]]
+                name = "tcp"
+                url = "tcp://example.com"
+                check_type = "Tcp"
+
+                [[checks]]
+                name = "tls"
+                url = "http://example.com"
+                check_type = "TlsCert"
+
+                [[checks]]
+                name = "dns"
+                url = "dns://example.com?type=SRV"
+                check_type = "Dns"
+
+                [[checks]]
+                name = "command"
+                check_type = "Command"
+
+                [[checks]]
+                name = "postgres"
+                url = "mysql://db.example.com"
+                check_type = "Postgres"
+
+                [[checks]]
+                name = "grpc"
+                url = "grpc://grpc.example.com"
+                check_type = "Grpc"
+
+                [[checks]]
+                name = "redis"
+                check_type = "Redis"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.validate(),
+            vec![
+                "Color 'up': 'green' is not a valid hex color like '#00FF00'",
+                "Color 'down': '#FF00' is not a valid hex color like '#00FF00'",
+                "Check 'http': URL 'tcp://example.com:80' must use scheme 'http' or 'https' for check type 'Http'",
+                "Check 'http': name is used by another check",
+                "Check 'http': URL 'example.com' is not valid: relative URL without a base",
+                "Check 'tcp': URL 'tcp://example.com' must contain host and port for check type 'Tcp'",
+                "Check 'tls': URL 'http://example.com' must use scheme 'tls' or 'https' for check type 'TlsCert'",
+                "Check 'dns': URL 'dns://example.com?type=SRV' is not valid: unsupported record type 'SRV'",
+                "Check 'command': 'command' is required for check type 'Command'",
+                "Check 'postgres': URL 'mysql://db.example.com' must use scheme 'postgres' or 'postgresql' for check type 'Postgres'",
+                "Check 'grpc': URL 'grpc://grpc.example.com' must contain host and port for check type 'Grpc'",
+                "Check 'redis': 'url' is required for check type 'Redis'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_should_validate_headers_and_authentication() {
+        let config: Config = toml::from_str(
+            r##"
+                [[checks]]
+                name = "http"
+                url = "https://example.com"
+                bearer_token = "secret"
+                headers = { "X-Api-Key" = "secret", "Invalid Header" = "value" }
+                warn_latency = "5s"
+                down_latency = "5s"
+
+                [checks.basic_auth]
+                username = "user"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.validate(),
+            vec![
+                "Check 'http': 'warn_latency' must be less than 'down_latency'",
+                "Check 'http': Only one of 'basic_auth' and 'bearer_token' can be used",
+                "Check 'http': Header name 'Invalid Header' is not valid",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_should_validate_tls_options() {
+        let config: Config = toml::from_str(
+            r##"
+                [[checks]]
+                name = "valid"
+                url = "https://example.com"
+                ca_file = "tests/tls/ca.pem"
+                client_cert = "tests/tls/client.pem"
+                client_key = "tests/tls/client.key"
+
+                [[checks]]
+                name = "invalid"
+                url = "https://example.com"
+                ca_file = "tests/tls/missing.pem"
+                client_cert = "tests/tls/client.pem"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.validate(),
+            vec![
+                "Check 'invalid': 'client_cert' and 'client_key' must be used together",
+                "Check 'invalid': File 'tests/tls/missing.pem' of 'ca_file' does not exist",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_should_parse_method_body_and_expected_status() {
+        let config: Config = toml::from_str(
+            r#"
+                [[checks]]
+                name = "post"
+                url = "https://example.com"
+                method = "post"
+                body = "{}"
+                expected_status = "200-299,401"
+
+                [[checks]]
+                name = "redirect"
+                url = "https://example.com"
+                expected_status = 301
+
+                [[checks]]
+                name = "list"
+                url = "https://example.com"
+                expected_status = [204, "300-302"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.checks[0].method, Some(Method::POST));
+        assert_eq!(config.checks[0].body, Some("{}".to_string()));
+        assert_eq!(
+            config.checks[0].expected_status,
+            Some(ExpectedStatus(vec![200..=299, 401..=401]))
+        );
+        assert_eq!(
+            config.checks[1].expected_status,
+            Some(ExpectedStatus(vec![301..=301]))
+        );
+        assert_eq!(
+            config.checks[2].expected_status,
+            Some(ExpectedStatus(vec![204..=204, 300..=302]))
+        );
+        assert!(config.checks[2].is_expected_status(StatusCode::FOUND));
+        assert!(!config.checks[2].is_expected_status(StatusCode::OK));
+    }
+
+    #[test]
+    fn test_should_not_parse_invalid_expected_status() {
+        assert_eq!(
+            "200-2x9".parse::<ExpectedStatus>(),
+            Err("invalid status code '2x9'".to_string())
+        );
+        assert_eq!(
+            "299-200".parse::<ExpectedStatus>(),
+            Err("invalid status code range '299-200'".to_string())
+        );
+        assert!(toml::from_str::<Config>(
+            r#"
+                [[checks]]
+                name = "invalid"
+                url = "https://example.com"
+                expected_status = "ok"
+            "#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_should_check_body_assertions() {
+        let config: Config = toml::from_str(
+            r#"
+                [[checks]]
+                name = "assertions"
+                url = "https://example.com"
+                body_contains = "status"
+                body_regex = "\"version\":\\s*\"2\\."
+                json_path = '$.db.status == "ok"'
+            "#,
+        )
+        .unwrap();
+        let check_config = &config.checks[0];
+
+        assert!(check_config.has_body_assertions());
+        assert!(check_config.is_expected_body(r#"{"version": "2.1", "db": {"status": "ok"}}"#));
+        assert!(!check_config.is_expected_body(r#"{"version": "1.9", "db": {"status": "ok"}}"#));
+        assert!(!check_config.is_expected_body(r#"{"version": "2.1", "db": {"status": "failed"}}"#));
+        assert!(!check_config.is_expected_body(r#"status "version": "2.1""#));
+    }
+
+    #[test]
+    fn test_should_not_parse_invalid_body_regex() {
+        assert!(toml::from_str::<Config>(
+            r#"
+                [[checks]]
+                name = "invalid"
+                url = "https://example.com"
+                body_regex = "(unclosed"
+            "#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_should_parse_payload_and_reply_assertions() {
+        let config: Config = toml::from_str(
+            r#"
+                [[checks]]
+                name = "text"
+                url = "udp://example.com:514"
+                check_type = "Udp"
+                send = "ping"
+                expect = "pong"
+                expect_regex = "^pong \\d+$"
+
+                [[checks]]
+                name = "hex"
+                url = "udp://example.com:27015"
+                check_type = "Udp"
+                send_hex = "FF ff ff ff 54"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.checks[0].payload(), Some(b"ping".to_vec()));
+        assert!(config.checks[0].has_reply_assertions());
+        assert!(config.checks[0].is_expected_reply("pong 42"));
+        assert!(!config.checks[0].is_expected_reply("pong"));
+        assert_eq!(
+            config.checks[1].payload(),
+            Some(vec![0xff, 0xff, 0xff, 0xff, 0x54])
+        );
+        assert!(!config.checks[1].has_reply_assertions());
+        assert!(config.checks[1].is_expected_reply("anything"));
+    }
+
+    #[test]
+    fn test_should_not_parse_invalid_hex_payload() {
+        assert!(toml::from_str::<Config>(
+            r#"
+                [[checks]]
+                name = "invalid"
+                url = "udp://example.com:514"
+                send_hex = "abc"
+            "#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_should_not_validate_missing_file() {
+        assert_eq!(
+            Config::validate_file("./tests/no_testconfig.toml").err(),
+            Some(vec![
+                "Cannot read ./tests/no_testconfig.toml: file not found".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_should_validate_file() {
+        assert!(Config::validate_file("./tests/testconfig1.toml").is_ok());
+    }
+
+    #[test]
+    fn test_should_apply_config_overrides() {
+        let mut config: Config = toml::from_str(
+            r#"
+                interval = 10
+
+                [[checks]]
+                name = "first"
+                url = "https://first.example.com"
+
+                [[checks]]
+                name = "second"
+                url = "https://second.example.com"
+                interval = 30
+            "#,
+        )
+        .unwrap();
+
+        ConfigOverrides {
+            interval: Some(Duration::from_secs(5)),
+            only: vec!["second".to_string()],
+        }
+        .apply(&mut config)
+        .unwrap();
+
+        assert_eq!(config.interval, Duration::from_secs(5));
+        assert_eq!(config.checks.len(), 1);
+        assert_eq!(config.checks[0].name, "second");
+        assert_eq!(
+            config.interval_for(&config.checks[0]),
+            Duration::from_secs(5)
+        );
+    }
+
+    #[test]
+    fn test_should_return_error_if_only_names_match_no_check() {
+        let mut config: Config = toml::from_str(
+            r#"
+                [[checks]]
+                name = "first"
+                url = "https://first.example.com"
+            "#,
+        )
+        .unwrap();
+
+        let result = ConfigOverrides {
+            interval: None,
+            only: vec!["first".to_string(), "nosuch".to_string()],
+        }
+        .apply(&mut config);
+
+        match result {
+            Err(err @ ConfigError::UnknownChecks(_)) => {
+                assert_eq!(err.to_string(), "No check configured with name 'nosuch'")
+            }
+            _ => panic!("Expected unknown checks error"),
+        }
+    }
+
+    #[test]
+    fn test_should_interpolate_environment_variables() {
+        env::set_var("CHECKBAR_TEST_TOKEN", "secret");
+        env::set_var("CHECKBAR_TEST_HOST", "example.com");
+
+        assert_eq!(
+            interpolate(
+                "https://${CHECKBAR_TEST_HOST}/health?token=${CHECKBAR_TEST_TOKEN}",
+                true
+            ),
+            Ok("https://example.com/health?token=secret".to_string())
+        );
+    }
+
+    #[test]
+    fn test_should_return_error_for_missing_environment_variable() {
+        assert_eq!(
+            interpolate("https://example.com/${CHECKBAR_TEST_MISSING}", true),
+            Err("environment variable 'CHECKBAR_TEST_MISSING' is not set".to_string())
+        );
+    }
+
+    #[test]
+    fn test_should_return_error_for_unterminated_reference() {
+        assert_eq!(
+            interpolate("https://example.com/${CHECKBAR_TEST_TOKEN", true),
+            Err("missing '}' after '${'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_should_interpolate_secret_files_and_commands() {
+        let filename = temp_config_file("secret").with_file_name("secret");
+        fs::write(&filename, "file-secret\n").unwrap();
+
+        assert_eq!(
+            interpolate(
+                &format!(
+                    "{{file:{}}}:{{cmd:echo cmd-secret}}",
+                    filename.to_str().unwrap()
+                ),
+                true
+            ),
+            Ok("file-secret:cmd-secret".to_string())
+        );
+        assert!(interpolate("{cmd:exit 1}", true).is_err());
+
+        let _ = fs::remove_dir_all(filename.parent().unwrap());
+    }
+
+    #[test]
+    fn test_should_interpolate_commands_containing_braces() {
+        assert_eq!(
+            interpolate(
+                "{cmd:echo user secret | awk '{print $2}'}@example.com",
+                true
+            ),
+            Ok("secret@example.com".to_string())
+        );
+    }
+
+    #[test]
+    fn test_should_not_resolve_references_if_disabled() {
+        assert_eq!(
+            interpolate("https://{cmd:pass show app/host}/health", false),
+            Ok("https://{cmd:pass show app/host}/health".to_string())
+        );
+        assert_eq!(
+            interpolate(
+                "{file:/run/secrets/checkbar_missing}:${CHECKBAR_TEST_MISSING}",
+                false
+            ),
+            Ok("{file:/run/secrets/checkbar_missing}:${CHECKBAR_TEST_MISSING}".to_string())
+        );
+        assert_eq!(
+            interpolate("https://example.com/${}", false),
+            Err("empty reference '${}'".to_string())
+        );
+        assert_eq!(
+            interpolate("https://example.com/{file:", false),
+            Err("missing '}' after '{file:'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_should_list_all_reference_problems_on_validation() {
+        let path = temp_config_file("validate-references");
+        let filename = path.to_str().unwrap();
+        fs::write(
+            filename,
+            r#"
+                [[checks]]
+                name = "secret"
+                url = "https://example.com/health"
+                bearer_token = "{file:/run/secrets/checkbar_missing}"
+
+                [[checks]]
+                name = "first"
+                url = "https://example.com/${CHECKBAR_TEST_HOST"
+
+                [[checks]]
+                name = "second"
+                url = "https://example.com"
+                body = "{cmd:}"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Config::validate_file(filename).err(),
+            Some(vec![
+                "Cannot resolve value of check 'first': missing '}' after '${'".to_string(),
+                "Cannot resolve value of check 'second': empty reference '{cmd:}'".to_string(),
+            ])
+        );
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_should_report_check_with_unresolvable_value() {
+        let path = temp_config_file("report-unresolvable");
+        let filename = path.to_str().unwrap();
+        fs::write(
+            filename,
+            r#"
+                [[checks]]
+                name = "example"
+                url = "https://example.com"
+                click_cmd = "xterm -e ssh ${CHECKBAR_TEST_MISSING_USER}@example.com"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Config::read_file(filename).err(),
+            Some(ConfigError::Interpolate(
+                "example".to_string(),
+                "environment variable 'CHECKBAR_TEST_MISSING_USER' is not set".to_string()
+            ))
+        );
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_should_use_default_shared_config_if_no_config_file() {
+        let config = SharedConfig::new("./tests/no_testconfig.toml", ConfigOverrides::default());
+        assert_eq!(config.get().interval, Duration::from_secs(60));
+        assert!(config.error().is_none());
+    }
+
+    #[test]
+    fn test_should_reload_shared_config() {
+        let path = temp_config_file("reload-shared-config");
+        let filename = path.to_str().unwrap();
+        fs::write(filename, "interval = 10").unwrap();
+
+        let config = SharedConfig::new(filename, ConfigOverrides::default());
+        assert_eq!(config.get().interval, Duration::from_secs(10));
+
+        fs::write(filename, "interval = 20").unwrap();
+        assert_eq!(config.get().interval, Duration::from_secs(10));
+        config.reload();
+        assert_eq!(config.get().interval, Duration::from_secs(20));
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
     }
 }
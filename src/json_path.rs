@@ -0,0 +1,194 @@
+use std::str::FromStr;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// Assertion on a JSON document using a simple JSON path like `$.db.status == "ok"`.
+///
+/// Supported are member access using `.name` or `['name']` and array access using `[index]`.
+/// Without comparison, the assertion holds if the path exists and its value is neither `null` nor `false`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonPathAssertion {
+    path: Vec<Segment>,
+    comparison: Option<(Operator, Value)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Member(String),
+    Index(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+}
+
+impl JsonPathAssertion {
+    pub fn matches(&self, document: &Value) -> bool {
+        let value = self
+            .path
+            .iter()
+            .try_fold(document, |value, segment| match segment {
+                Segment::Member(name) => value.get(name),
+                Segment::Index(index) => value.get(index),
+            });
+
+        match (&self.comparison, value) {
+            (Some((Operator::Equal, expected)), Some(value)) => value == expected,
+            (Some((Operator::NotEqual, expected)), Some(value)) => value != expected,
+            (Some((Operator::NotEqual, _)), None) => true,
+            (None, Some(value)) => !matches!(value, Value::Null | Value::Bool(false)),
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for JsonPathAssertion {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        // Operators are only looked for after the path, which may contain them within quoted member names
+        let (path, rest) = parse_path(value)?;
+        let rest = rest.trim_start();
+        let comparison = match rest.split_at_checked(2) {
+            Some((operator @ ("==" | "!="), expected)) => {
+                let operator = if operator == "==" {
+                    Operator::Equal
+                } else {
+                    Operator::NotEqual
+                };
+                let expected = expected.trim();
+                let expected = serde_json::from_str::<Value>(expected)
+                    .map_err(|_| format!("invalid JSON value '{expected}'"))?;
+                Some((operator, expected))
+            }
+            _ if rest.is_empty() => None,
+            _ => return Err(format!("JSON path '{value}' is not valid")),
+        };
+
+        Ok(Self { path, comparison })
+    }
+}
+
+/// Parses JSON path at start of given value and returns its segments and the remaining value.
+fn parse_path(value: &str) -> Result<(Vec<Segment>, &str), String> {
+    let path = value.split_whitespace().next().unwrap_or_default();
+    let mut rest = value
+        .strip_prefix('$')
+        .ok_or_else(|| format!("JSON path '{path}' must start with '$'"))?;
+    let mut segments = vec![];
+
+    loop {
+        if let Some(member) = rest.strip_prefix('.') {
+            let end = member
+                .find(|c: char| matches!(c, '.' | '[' | '=' | '!') || c.is_whitespace())
+                .unwrap_or(member.len());
+            if end == 0 {
+                return Err(format!("JSON path '{path}' contains empty member name"));
+            }
+            segments.push(Segment::Member(member[..end].to_string()));
+            rest = &member[end..];
+        } else if let Some(subscript) = rest.strip_prefix('[') {
+            let subscript = subscript.trim_start();
+            let (segment, remaining) = match subscript.strip_prefix('\'') {
+                Some(quoted) => {
+                    let end = quoted
+                        .find('\'')
+                        .ok_or_else(|| format!("JSON path '{path}' is missing closing quote"))?;
+                    (
+                        Segment::Member(quoted[..end].to_string()),
+                        &quoted[end + 1..],
+                    )
+                }
+                None => {
+                    let end = subscript.find(']').unwrap_or(subscript.len());
+                    let index = subscript[..end]
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("JSON path '{path}' contains invalid index"))?;
+                    (Segment::Index(index), &subscript[end..])
+                }
+            };
+            rest = remaining
+                .trim_start()
+                .strip_prefix(']')
+                .ok_or_else(|| format!("JSON path '{path}' is missing ']'"))?;
+            segments.push(segment);
+        } else {
+            return Ok((segments, rest));
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonPathAssertion {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(d)?.parse().map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::json_path::JsonPathAssertion;
+
+    fn assertion(value: &str) -> JsonPathAssertion {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_should_match_equal_value() {
+        let document = json!({"db": {"status": "ok"}, "checks": [{"up": true}]});
+
+        assert!(assertion(r#"$.db.status == "ok""#).matches(&document));
+        assert!(assertion(r#"$['db']['status']=="ok""#).matches(&document));
+        assert!(assertion("$.checks[0].up == true").matches(&document));
+        assert!(!assertion(r#"$.db.status == "failed""#).matches(&document));
+        assert!(!assertion(r#"$.cache.status == "ok""#).matches(&document));
+    }
+
+    #[test]
+    fn test_should_match_not_equal_value() {
+        let document = json!({"db": {"status": "ok"}});
+
+        assert!(assertion(r#"$.db.status != "failed""#).matches(&document));
+        assert!(!assertion(r#"$.db.status != "ok""#).matches(&document));
+    }
+
+    #[test]
+    fn test_should_parse_operators_within_quoted_values() {
+        let document = json!({"a": "x==y", "a==b": 1});
+
+        assert!(assertion(r#"$.a != "x!=y""#).matches(&document));
+        assert!(assertion(r#"$.a == "x==y""#).matches(&document));
+        assert!(assertion("$['a==b'] == 1").matches(&document));
+        assert!(assertion("$['a==b']").matches(&document));
+    }
+
+    #[test]
+    fn test_should_match_existing_value() {
+        let document = json!({"db": {"status": "ok", "replica": null}, "up": false});
+
+        assert!(assertion("$.db.status").matches(&document));
+        assert!(!assertion("$.db.replica").matches(&document));
+        assert!(!assertion("$.up").matches(&document));
+        assert!(!assertion("$.cache").matches(&document));
+    }
+
+    #[test]
+    fn test_should_not_parse_invalid_assertions() {
+        assert!("db.status".parse::<JsonPathAssertion>().is_err());
+        assert!("$..status".parse::<JsonPathAssertion>().is_err());
+        assert!("$.checks[x]".parse::<JsonPathAssertion>().is_err());
+        assert!("$.db.status == ok".parse::<JsonPathAssertion>().is_err());
+        assert!("$.db.status = \"ok\"".parse::<JsonPathAssertion>().is_err());
+        assert!("$['db".parse::<JsonPathAssertion>().is_err());
+    }
+}
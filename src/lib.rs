@@ -1,16 +1,27 @@
 mod checker;
 mod config;
+mod json_path;
+mod output;
+mod scheduler;
+mod watcher;
 
 use console::{style, Term};
+use futures::stream::{self, StreamExt};
+use std::collections::HashMap;
 use std::io::Write;
 use std::process;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
 
-pub use config::{CheckConfig, Config};
+pub use config::{CheckConfig, Config, ConfigError, ConfigOverrides, SharedConfig};
+pub use output::{Output, OutputOptions};
+pub use scheduler::Scheduler;
 use serde::Deserialize;
 use serde_json::json;
 use serde_repr::Deserialize_repr;
+pub use watcher::{reload_on_hangup, watch_config_file};
 
-use checker::check_host;
+use checker::{check_host, CheckResult};
 
 #[derive(Debug, Deserialize_repr, PartialEq)]
 #[repr(u8)]
@@ -26,38 +37,175 @@ pub struct ClickEvent {
     pub button: MouseButton,
 }
 
-pub async fn print_states(config: &Config) {
-    let mut entries = vec![];
-    if Term::stdout().is_term() {
-        for check_config in &config.checks {
-            entries.push(format!("{}", check_host(check_config).await));
+/// Latest check results by check name, shared between check scheduler and renderer.
+#[derive(Clone, Default)]
+pub struct CheckResults {
+    results: Arc<Mutex<HashMap<String, CheckResult>>>,
+    changed: Arc<Notify>,
+}
+
+impl CheckResults {
+    fn update(&self, check_result: CheckResult) {
+        self.results
+            .lock()
+            .unwrap()
+            .insert(check_result.name.to_string(), check_result);
+        self.changed.notify_one();
+    }
+
+    /// Waits until any check result has been updated.
+    pub async fn changed(&self) {
+        self.changed.notified().await
+    }
+}
+
+/// Runs all configured checks concurrently, limited to `config.concurrency` checks at once.
+/// Each result is stored as soon as its check has been completed.
+pub async fn run_checks(config: &Config, check_results: &CheckResults) {
+    let checks = config
+        .checks
+        .iter()
+        .map(|check_config| check_host(check_config, config.timeout_for(check_config)))
+        .collect::<Vec<_>>();
+
+    stream::iter(checks)
+        .buffer_unordered(config.concurrency.max(1))
+        .for_each(|check_result| async { check_results.update(check_result) })
+        .await
+}
+
+/// Runs all configured checks once and prints a summary, or a single JSON line if JSON output is used.
+/// Returns the exit code of the worst check state.
+pub async fn run_once(config: &Config, output_options: &OutputOptions) -> i32 {
+    let check_results = CheckResults::default();
+    run_checks(config, &check_results).await;
+
+    let results = check_results.results.lock().unwrap();
+    let results = config
+        .checks
+        .iter()
+        .filter_map(|check_config| results.get(&check_config.name))
+        .collect::<Vec<_>>();
+
+    if output_options.output == Output::Json {
+        let entries = results
+            .iter()
+            .map(|check_result| check_result.format(output_options, &config.colors))
+            .collect::<Vec<_>>();
+        println!("{}", to_json_line(config, None, &entries));
+    } else {
+        for check_result in &results {
+            let mut line = format!(
+                "{:<8} {}",
+                format!("{:?}", check_result.state),
+                check_result.name
+            );
+            if let Some(latency) = check_result.latency {
+                line.push_str(&format!(" ({} ms)", latency.as_millis()));
+            }
+            if let Some(message) = &check_result.message {
+                line.push_str(&format!(": {message}"));
+            }
+            println!("{line}");
         }
-        entries.push(
-            chrono::Local::now()
-                .format(config.time_format.as_str())
-                .to_string(),
-        );
-        println!("{}", entries.join(&style(" | ").black().to_string()));
-        return;
     }
 
-    print!("[");
-    for check_config in &config.checks {
-        entries.push(format!("{}", check_host(check_config).await));
+    results
+        .iter()
+        .map(|check_result| check_result.state)
+        .max_by_key(|state| state.severity())
+        .map_or(0, |state| state.exit_code())
+}
+
+/// Prints latest check results in configured order. Checks without a result yet are omitted.
+/// A configuration error is shown in front of all check results.
+pub fn print_states(
+    shared_config: &SharedConfig,
+    check_results: &CheckResults,
+    output_options: &OutputOptions,
+) {
+    let config = shared_config.get();
+    let config_error = shared_config.error();
+
+    let mut entries = {
+        let results = check_results.results.lock().unwrap();
+        config
+            .checks
+            .iter()
+            .filter_map(|check_config| results.get(&check_config.name))
+            .map(|check_result| check_result.format(output_options, &config.colors))
+            .collect::<Vec<_>>()
+    };
+
+    match output_options.output {
+        Output::Terminal => {
+            if let Some(config_error) = config_error {
+                let config_error = if output_options.colors {
+                    style(config_error).red().to_string()
+                } else {
+                    config_error.to_string()
+                };
+                entries.insert(0, config_error);
+            }
+            entries.push(
+                chrono::Local::now()
+                    .format(config.time_format.as_str())
+                    .to_string(),
+            );
+            let separator = if output_options.colors {
+                style(" | ").black().to_string()
+            } else {
+                " | ".to_string()
+            };
+            let term = Term::stdout();
+            if term.is_term() {
+                let _ = term.clear_line();
+                let _ = term.write_str(&entries.join(&separator));
+                let _ = term.hide_cursor();
+            } else {
+                println!("{}", entries.join(&separator));
+            }
+        }
+        Output::I3bar => {
+            if let Some(config_error) = config_error {
+                let mut entry = json!({
+                    "full_text": config_error.to_string(),
+                    "name": "config_error"
+                });
+                if output_options.colors {
+                    entry["color"] = json!(config.colors.down);
+                }
+                entries.insert(0, entry.to_string());
+            }
+            print!("[");
+            entries.push(
+                json!({
+                    "full_text": chrono::Local::now().format(config.time_format.as_str()).to_string()
+                })
+                .to_string(),
+            );
+            println!("{}],", entries.join(","));
+        }
+        Output::Json => println!("{}", to_json_line(&config, config_error.as_ref(), &entries)),
     }
-    entries.push(
-        json!({
-            "full_text": chrono::Local::now().format(config.time_format.as_str()).to_string()
-        })
-        .to_string(),
-    );
-    println!("{}],", entries.join(","));
 }
 
-pub async fn get_click_cmd(name: String) -> Option<String> {
-    for check in Config::read().checks {
+/// Joins JSON formatted check results into a single JSON object including time and configuration error.
+fn to_json_line(config: &Config, config_error: Option<&ConfigError>, entries: &[String]) -> String {
+    format!(
+        r#"{{"time":{},"error":{},"checks":[{}]}}"#,
+        json!(chrono::Local::now()
+            .format(config.time_format.as_str())
+            .to_string()),
+        json!(config_error.map(|err| err.to_string())),
+        entries.join(",")
+    )
+}
+
+pub async fn get_click_cmd(config: &Config, name: String) -> Option<String> {
+    for check in &config.checks {
         if check.name == name {
-            return check.click_cmd;
+            return check.click_cmd.clone();
         }
     }
     None
@@ -89,7 +237,11 @@ pub fn read_click_event() -> Result<ClickEvent, String> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{ClickEvent, MouseButton};
+    use crate::checker::CheckState;
+    use crate::{
+        run_checks, run_once, CheckResults, ClickEvent, Config, MouseButton, OutputOptions,
+    };
+    use tokio::net::TcpListener;
 
     #[test]
     fn test_should_deserialize_click_event() {
@@ -103,4 +255,67 @@ mod tests {
         let actual = actual.unwrap();
         assert_eq!(actual, expected);
     }
+
+    #[tokio::test]
+    async fn test_should_store_check_results_by_name() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config: Config = toml::from_str(&format!(
+            r#"
+                [[checks]]
+                name = "tcp"
+                url = "tcp://{}"
+                check_type = "Tcp"
+            "#,
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+
+        let check_results = CheckResults::default();
+        run_checks(&config, &check_results).await;
+
+        let results = check_results.results.lock().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results["tcp"].state, CheckState::Up);
+    }
+
+    #[tokio::test]
+    async fn test_should_return_exit_code_of_worst_state() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut config: Config = toml::from_str(&format!(
+            r#"
+                [[checks]]
+                name = "up"
+                url = "tcp://{}"
+                check_type = "Tcp"
+
+                [[checks]]
+                name = "down"
+                url = "tcp://{}"
+                check_type = "Tcp"
+            "#,
+            listener.local_addr().unwrap(),
+            closed_listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        drop(closed_listener);
+
+        assert_eq!(run_once(&config, &OutputOptions::default()).await, 2);
+
+        // Down is worse than unknown, although its exit code is lower
+        config.checks.push(
+            toml::from_str(
+                r#"
+                    name = "unknown"
+                    check_type = "Command"
+                    command = "exit 3"
+                "#,
+            )
+            .unwrap(),
+        );
+        assert_eq!(run_once(&config, &OutputOptions::default()).await, 2);
+
+        config.checks.remove(1);
+        assert_eq!(run_once(&config, &OutputOptions::default()).await, 3);
+    }
 }
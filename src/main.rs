@@ -1,52 +1,216 @@
-use checkbar::{get_click_cmd, print_states, read_click_event, run_click_cmd, Config, MouseButton};
+use checkbar::{
+    get_click_cmd, print_states, read_click_event, reload_on_hangup, run_click_cmd, run_once,
+    watch_config_file, CheckResults, Config, ConfigOverrides, MouseButton, Output, OutputOptions,
+    Scheduler, SharedConfig,
+};
+use clap::{Parser, Subcommand};
 use console::Term;
+use parse_duration::parse;
 use serde_json::json;
 use std::process::exit;
+use std::time::Duration;
 use tokio::task;
-use tokio::time::sleep;
+use tokio::time::{sleep, sleep_until};
+
+/// Shows up/warn/down state of configured hosts or applications
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Configuration file, defaults to first existing of `$XDG_CONFIG_HOME/checkbar/config.toml`,
+    /// `$HOME/.checkbar.toml` and `/etc/checkbar/config.toml`
+    #[arg(short, long, global = true)]
+    config: Option<String>,
+
+    /// Configuration file, same as `--config`
+    #[arg(hide = true)]
+    config_file: Option<String>,
+
+    /// Update interval of all checks, overrides any interval in configuration file
+    #[arg(short, long, value_parser = parse)]
+    interval: Option<Duration>,
+
+    /// Output format, guessed by checking whether stdout is a terminal if not set
+    #[arg(short, long, value_enum, global = true)]
+    output: Option<Output>,
+
+    /// Run only checks with given name, can be used multiple times. Names must match configured checks
+    #[arg(long, value_name = "NAME", global = true)]
+    only: Vec<String>,
+
+    /// Do not use colors
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Run all checks once, same as `once` command
+    #[arg(long)]
+    once: bool,
+
+    /// Validate configuration file, same as `check-config` command
+    #[arg(long)]
+    validate: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run all checks once and exit with Nagios-style exit code of worst check state
+    Once {
+        /// Configuration file, same as `--config`
+        config_file: Option<String>,
+    },
+    /// Validate configuration file and list all problems found
+    #[command(visible_alias = "validate")]
+    CheckConfig {
+        /// Configuration file, same as `--config`
+        config_file: Option<String>,
+    },
+}
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 2)]
 async fn main() {
-    if Term::stdout().is_term() {
-        let _ = Term::stdout().hide_cursor();
-        let _ = ctrlc::set_handler(|| {
-            let _ = Term::stdout().show_cursor();
-            println!();
-            exit(1);
+    let cli = Cli::parse();
+    let output_options = OutputOptions::new(cli.output, cli.no_color);
+    let overrides = ConfigOverrides {
+        interval: cli.interval,
+        only: cli.only,
+    };
+
+    let config_file = cli.config.or(cli.config_file);
+    let command = match cli.command {
+        None if cli.validate => Some(Command::CheckConfig { config_file: None }),
+        None if cli.once => Some(Command::Once { config_file: None }),
+        command => command,
+    };
+
+    match command {
+        Some(Command::CheckConfig {
+            config_file: command_config_file,
+        }) => exit(check_config(&Config::get_config_file(
+            config_file.or(command_config_file),
+        ))),
+        Some(Command::Once {
+            config_file: command_config_file,
+        }) => exit(
+            once(
+                &Config::get_config_file(config_file.or(command_config_file)),
+                &overrides,
+                &output_options,
+            )
+            .await,
+        ),
+        None => {}
+    }
+
+    match output_options.output {
+        Output::Terminal if Term::stdout().is_term() => {
+            let _ = Term::stdout().hide_cursor();
+            let _ = ctrlc::set_handler(|| {
+                let _ = Term::stdout().show_cursor();
+                println!();
+                exit(1);
+            });
+        }
+        Output::I3bar => {
+            println!(
+                "{}",
+                json!({
+                    "version": 1,
+                    "click_events": true
+                })
+            );
+            println!("[");
+        }
+        _ => {}
+    }
+
+    let config = SharedConfig::new(&Config::get_config_file(config_file), overrides);
+    // Keep watcher alive while running
+    let _watcher = watch_config_file(&config)
+        .map_err(|err| eprintln!("Cannot watch configuration file for changes: {err}"))
+        .ok();
+    task::spawn(reload_on_hangup(config.clone()));
+
+    // Click events are only available using i3bar input protocol
+    if output_options.output == Output::I3bar {
+        let config = config.clone();
+        task::spawn(async move {
+            loop {
+                if let Ok(click_event) = read_click_event() {
+                    // Ignore click event if not left mouse button
+                    if click_event.button != MouseButton::Left {
+                        continue;
+                    };
+                    if let Some(click_cmd) = get_click_cmd(&config.get(), click_event.name).await {
+                        run_click_cmd(click_cmd).await;
+                    }
+                }
+            }
         });
-    } else {
-        println!(
-            "{}",
-            json!({
-                "version": 1,
-                "click_events": true
-            })
-        );
-        println!("[");
     }
 
-    let inputs = task::spawn(async {
-        loop {
-            if let Ok(click_event) = read_click_event() {
-                // Ignore click event if not left mouse button
-                if click_event.button != MouseButton::Left {
-                    continue;
-                };
-                if let Some(click_cmd) = get_click_cmd(click_event.name).await {
-                    run_click_cmd(click_cmd).await;
+    let check_results = CheckResults::default();
+
+    let checks = task::spawn({
+        let mut scheduler = Scheduler::new(check_results.clone(), config.get().concurrency);
+        let mut config_changes = config.subscribe();
+        async move {
+            loop {
+                let next_run = scheduler.run_due_checks(&config_changes.borrow_and_update());
+                // Wait for next due check, or reschedule as soon as configuration changed
+                tokio::select! {
+                    _ = sleep_until(next_run) => {},
+                    _ = config_changes.changed() => {},
                 }
             }
         }
     });
 
-    let checks = task::spawn(async {
+    let render = task::spawn(async move {
         loop {
-            let config = Config::read();
-            print_states(&config).await;
-            let _ = Term::stdout().hide_cursor();
-            let _ = sleep(config.interval).await;
+            print_states(&config, &check_results, &output_options);
+            // Redraw every second to keep time up to date, or as soon as any check result changed
+            tokio::select! {
+                _ = sleep(Duration::from_secs(1)) => {},
+                _ = check_results.changed() => {},
+            }
         }
     });
 
-    let _r = tokio::join!(inputs, checks);
+    let _r = tokio::join!(checks, render);
+}
+
+/// Validates config file and prints found problems. Returns exit code.
+fn check_config(filename: &str) -> i32 {
+    match Config::validate_file(filename) {
+        Ok(config) => {
+            println!("{filename}: OK, {} checks configured", config.checks.len());
+            0
+        }
+        Err(problems) => {
+            eprintln!("{filename}: {} problem(s) found", problems.len());
+            for problem in problems {
+                eprintln!("  - {problem}");
+            }
+            1
+        }
+    }
+}
+
+/// Runs all checks once and returns exit code of worst check state.
+/// A missing config file is an error, as there would be nothing to check.
+async fn once(filename: &str, overrides: &ConfigOverrides, output_options: &OutputOptions) -> i32 {
+    let config = Config::read_existing_file(filename).and_then(|mut config| {
+        overrides.apply(&mut config)?;
+        Ok(config)
+    });
+    match config {
+        Ok(config) => run_once(&config, output_options).await,
+        // Use exit code not used by any check state, so configuration errors can be told apart
+        Err(err) => {
+            eprintln!("{err}");
+            4
+        }
+    }
 }
@@ -0,0 +1,71 @@
+use clap::ValueEnum;
+use console::Term;
+
+/// Output format used to render check results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    /// i3bar input protocol
+    I3bar,
+    /// Single line in terminal
+    Terminal,
+    /// One JSON object per line
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutputOptions {
+    pub output: Output,
+    pub colors: bool,
+}
+
+impl OutputOptions {
+    /// Uses given output or guesses it by checking whether stdout is a terminal.
+    /// Terminal output is only colored if supported by the terminal.
+    pub fn new(output: Option<Output>, no_color: bool) -> Self {
+        let term = Term::stdout();
+        let output = output.unwrap_or(if term.is_term() {
+            Output::Terminal
+        } else {
+            Output::I3bar
+        });
+        Self {
+            output,
+            colors: !no_color
+                && (output != Output::Terminal
+                    || (term.is_term() && term.features().colors_supported())),
+        }
+    }
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self::new(None, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::output::{Output, OutputOptions};
+
+    #[test]
+    fn test_should_use_given_output() {
+        assert_eq!(
+            OutputOptions::new(Some(Output::Json), false),
+            OutputOptions {
+                output: Output::Json,
+                colors: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_should_disable_colors() {
+        assert_eq!(
+            OutputOptions::new(Some(Output::I3bar), true),
+            OutputOptions {
+                output: Output::I3bar,
+                colors: false
+            }
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use std::time::Duration;
+
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::checker::check_host;
+use crate::config::Config;
+use crate::CheckResults;
+
+/// Keeps track of the next due time of each check and runs due checks in background,
+/// limited to `concurrency` checks at once.
+pub struct Scheduler {
+    check_results: CheckResults,
+    concurrency: usize,
+    semaphore: Arc<Semaphore>,
+    next_runs: HashMap<String, NextRun>,
+    running: HashMap<String, JoinHandle<()>>,
+}
+
+/// Due time of a check along with the time and interval of its last run.
+struct NextRun {
+    due: Instant,
+    last_run: Instant,
+    interval: Duration,
+}
+
+impl Scheduler {
+    pub fn new(check_results: CheckResults, concurrency: usize) -> Self {
+        Self {
+            check_results,
+            concurrency,
+            semaphore: Arc::new(Semaphore::new(concurrency.max(1))),
+            next_runs: HashMap::new(),
+            running: HashMap::new(),
+        }
+    }
+
+    /// Starts all checks due by now and returns the time the next check will be due.
+    /// A check still running from its last due time will not be started twice.
+    pub fn run_due_checks(&mut self, config: &Config) -> Instant {
+        let now = Instant::now();
+
+        // Checks already started keep their permits of the previous semaphore
+        if config.concurrency != self.concurrency {
+            self.concurrency = config.concurrency;
+            self.semaphore = Arc::new(Semaphore::new(config.concurrency.max(1)));
+        }
+
+        // Forget about checks no longer configured
+        self.next_runs
+            .retain(|name, _| config.checks.iter().any(|check| &check.name == name));
+        self.running.retain(|_, handle| !handle.is_finished());
+
+        for check_config in &config.checks {
+            let interval = config.interval_for(check_config);
+            let next_run = self
+                .next_runs
+                .entry(check_config.name.to_string())
+                .or_insert(NextRun {
+                    due: now,
+                    last_run: now,
+                    interval,
+                });
+            // A changed interval applies from the last run, but never postpones a due check
+            if next_run.interval != interval {
+                next_run.due = next_run.due.min(next_run.last_run + interval);
+                next_run.interval = interval;
+            }
+            if next_run.due > now {
+                continue;
+            }
+            next_run.due = now + interval;
+            next_run.last_run = now;
+
+            if self.running.contains_key(&check_config.name) {
+                continue;
+            }
+
+            let check_config = check_config.clone();
+            let timeout = config.timeout_for(&check_config);
+            let check_results = self.check_results.clone();
+            let semaphore = self.semaphore.clone();
+            self.running.insert(
+                check_config.name.to_string(),
+                tokio::spawn(async move {
+                    let _permit = semaphore.acquire().await;
+                    check_results.update(check_host(&check_config, timeout).await);
+                }),
+            );
+        }
+
+        self.next_runs
+            .values()
+            .map(|next_run| next_run.due)
+            .min()
+            .unwrap_or(now + config.interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::net::TcpListener;
+    use tokio::time::Instant;
+
+    use crate::config::Config;
+    use crate::scheduler::Scheduler;
+    use crate::CheckResults;
+
+    #[tokio::test]
+    async fn test_should_schedule_checks_by_their_interval() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config: Config = toml::from_str(&format!(
+            r#"
+                interval = "1m"
+
+                [[checks]]
+                name = "slow"
+                url = "tcp://{0}"
+                check_type = "Tcp"
+
+                [[checks]]
+                name = "fast"
+                url = "tcp://{0}"
+                check_type = "Tcp"
+                interval = "10s"
+            "#,
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+
+        let mut scheduler = Scheduler::new(CheckResults::default(), 2);
+        let start = Instant::now();
+        let next_run = scheduler.run_due_checks(&config);
+
+        assert!(next_run >= start + Duration::from_secs(10));
+        assert!(next_run < start + Duration::from_secs(11));
+        assert!(scheduler.next_runs["slow"].due >= start + Duration::from_secs(60));
+        assert_eq!(scheduler.running.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_should_apply_shortened_interval_from_last_run() {
+        let mut config: Config = toml::from_str(
+            r#"
+                [[checks]]
+                name = "changed"
+                url = "tcp://127.0.0.1:1"
+                check_type = "Tcp"
+                interval = "10m"
+            "#,
+        )
+        .unwrap();
+
+        let mut scheduler = Scheduler::new(CheckResults::default(), 1);
+        let start = Instant::now();
+        scheduler.run_due_checks(&config);
+        assert!(scheduler.next_runs["changed"].due >= start + Duration::from_secs(600));
+
+        config.checks[0].interval = Some(Duration::from_secs(10));
+        let next_run = scheduler.run_due_checks(&config);
+
+        assert!(next_run < start + Duration::from_secs(11));
+        assert_eq!(scheduler.next_runs["changed"].due, next_run);
+    }
+
+    #[tokio::test]
+    async fn test_should_forget_removed_checks() {
+        let mut config: Config = toml::from_str(
+            r#"
+                [[checks]]
+                name = "removed"
+                url = "tcp://127.0.0.1:1"
+                check_type = "Tcp"
+            "#,
+        )
+        .unwrap();
+
+        let mut scheduler = Scheduler::new(CheckResults::default(), 1);
+        scheduler.run_due_checks(&config);
+        assert_eq!(scheduler.next_runs.len(), 1);
+
+        config.checks.clear();
+        scheduler.run_due_checks(&config);
+        assert_eq!(scheduler.next_runs.len(), 0);
+    }
+}
@@ -0,0 +1,172 @@
+use std::path::{Path, PathBuf};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::task;
+
+use crate::config::{Config, SharedConfig};
+
+/// Watches the configuration file and its drop-in directory, if it exists,
+/// and reloads the shared configuration on any change.
+/// The parent directory is watched, since editors often replace files instead of writing them.
+/// The returned watcher must be kept alive as long as changes should be watched.
+/// Its events are handled on a thread of its own, so reloading does not block the runtime.
+pub fn watch_config_file(config: &SharedConfig) -> notify::Result<RecommendedWatcher> {
+    let filename = PathBuf::from(config.filename());
+    let directory = match filename.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let mut watcher = notify::recommended_watcher({
+        let config = config.clone();
+        let filename = filename.clone();
+        move |event: notify::Result<Event>| {
+            if let Ok(event) = event {
+                if is_relevant_event(&event, &filename) {
+                    config.reload();
+                }
+            }
+        }
+    })?;
+    watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+
+    let checks_dir = Config::get_checks_dir(config.filename());
+    if checks_dir.is_dir() {
+        watcher.watch(&checks_dir, RecursiveMode::NonRecursive)?;
+    }
+
+    Ok(watcher)
+}
+
+/// Removal of the configuration file is ignored, since editors replacing the file on save
+/// remove it before the new file is created. Removed drop-in files are reloaded.
+fn is_relevant_event(event: &Event, filename: &Path) -> bool {
+    let checks_dir = Config::get_checks_dir(&filename.to_string_lossy());
+    match event.kind {
+        EventKind::Create(_) | EventKind::Modify(_) => event.paths.iter().any(|path| {
+            path.file_name() == filename.file_name() || is_fragment_file(path, &checks_dir)
+        }),
+        EventKind::Remove(_) => event
+            .paths
+            .iter()
+            .any(|path| is_fragment_file(path, &checks_dir)),
+        _ => false,
+    }
+}
+
+fn is_fragment_file(path: &Path, checks_dir: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "toml")
+        && path.parent().and_then(|parent| parent.file_name()) == checks_dir.file_name()
+}
+
+/// Reloads the shared configuration whenever SIGHUP has been received.
+/// Reloading may run secret commands, so it is done on a thread allowed to block.
+pub async fn reload_on_hangup(config: SharedConfig) {
+    if let Ok(mut hangup) = signal(SignalKind::hangup()) {
+        while hangup.recv().await.is_some() {
+            let config = config.clone();
+            let _ = task::spawn_blocking(move || config.reload()).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use notify::event::{AccessKind, CreateKind, ModifyKind, RemoveKind};
+    use notify::{Event, EventKind};
+
+    use crate::watcher::is_relevant_event;
+
+    #[test]
+    fn test_should_accept_modification_of_config_file() {
+        let event = Event::new(EventKind::Modify(ModifyKind::Any))
+            .add_path(PathBuf::from("/home/user/.checkbar.toml"));
+
+        assert!(is_relevant_event(
+            &event,
+            &PathBuf::from("/home/user/.checkbar.toml")
+        ));
+    }
+
+    #[test]
+    fn test_should_accept_replaced_config_file() {
+        let event = Event::new(EventKind::Create(CreateKind::File))
+            .add_path(PathBuf::from("/home/user/.checkbar.toml"));
+
+        assert!(is_relevant_event(
+            &event,
+            &PathBuf::from("/home/user/.checkbar.toml")
+        ));
+    }
+
+    #[test]
+    fn test_should_accept_modification_of_drop_in_file() {
+        let event = Event::new(EventKind::Modify(ModifyKind::Any)).add_path(PathBuf::from(
+            "/home/user/.config/checkbar/checks.d/team.toml",
+        ));
+
+        assert!(is_relevant_event(
+            &event,
+            &PathBuf::from("/home/user/.config/checkbar/config.toml")
+        ));
+    }
+
+    #[test]
+    fn test_should_ignore_removal_of_config_file() {
+        let event = Event::new(EventKind::Remove(RemoveKind::File))
+            .add_path(PathBuf::from("/home/user/.checkbar.toml"));
+
+        assert!(!is_relevant_event(
+            &event,
+            &PathBuf::from("/home/user/.checkbar.toml")
+        ));
+    }
+
+    #[test]
+    fn test_should_accept_removal_of_drop_in_file() {
+        let event = Event::new(EventKind::Remove(RemoveKind::File)).add_path(PathBuf::from(
+            "/home/user/.config/checkbar/checks.d/team.toml",
+        ));
+
+        assert!(is_relevant_event(
+            &event,
+            &PathBuf::from("/home/user/.config/checkbar/config.toml")
+        ));
+    }
+
+    #[test]
+    fn test_should_accept_modification_of_drop_in_file_named_after_config_file() {
+        let event = Event::new(EventKind::Modify(ModifyKind::Any))
+            .add_path(PathBuf::from("/home/user/.checkbar.d/team.toml"));
+
+        assert!(is_relevant_event(
+            &event,
+            &PathBuf::from("/home/user/.checkbar.toml")
+        ));
+    }
+
+    #[test]
+    fn test_should_ignore_other_files() {
+        let event = Event::new(EventKind::Modify(ModifyKind::Any))
+            .add_path(PathBuf::from("/home/user/.bashrc"));
+
+        assert!(!is_relevant_event(
+            &event,
+            &PathBuf::from("/home/user/.checkbar.toml")
+        ));
+    }
+
+    #[test]
+    fn test_should_ignore_access_to_config_file() {
+        let event = Event::new(EventKind::Access(AccessKind::Any))
+            .add_path(PathBuf::from("/home/user/.checkbar.toml"));
+
+        assert!(!is_relevant_event(
+            &event,
+            &PathBuf::from("/home/user/.checkbar.toml")
+        ));
+    }
+}